@@ -1,3 +1,4 @@
+use crate::error::{AlignError, AlignResult};
 use crate::*;
 
 pub struct AlignContext {
@@ -6,30 +7,34 @@ pub struct AlignContext {
 
 // default+-
 impl AlignContext {
-    pub fn new() -> Self {
-        Self {
-            similarity: Similarity::new(),
-        }
+    pub fn new() -> AlignResult<Self> {
+        Ok(Self {
+            similarity: Similarity::new()?,
+        })
     }
 }
 
+/// Path found in a window and similarity matrix of the window (rows are `ys`).
+pub type WindowAlignment = (Vec<(usize, usize)>, Vec<Vec<f32>>);
+
 // Given two string vectors,
 // returns generally diagonal path in matrix (as X bs) of semantic similarity,
 // that has in it most semantic alignment captured.
 // The path can connect any two sides of matrix so it is like a local alignment,
 // as opposed to global, that would connect two opposing corners.
+// Non-flexible start/end pin the path to the corresponding corner (anchors, realignment).
 //
 // Simplistic alignment implementation should work in general case (Dijkstra search).
 //
 ///
 /// Simplest square diagonal case, each item gets aligned to the corresponding item.
 /// ```
-/// use translate::*;
-/// let ctx = AlignContext::new();
+/// use make_parallel_text::*;
+/// let ctx = AlignContext::new().unwrap();
 ///
 /// let xs = vec!["content text", "second piece", "third part"];
 /// let ys = vec!["content water", "second thing", "third chunk"];
-/// let (got, _) = alignment_path(&xs, &ys, true, &ctx);
+/// let (got, _) = alignment_path(&xs, &ys, true, true, &ctx).unwrap();
 /// assert_eq!(got, vec![
 ///   (0, 0),
 ///   (1, 1),
@@ -39,12 +44,12 @@ impl AlignContext {
 ///
 /// Non-square case
 /// ```
-/// use translate::*;
-/// let ctx = AlignContext::new();
+/// use make_parallel_text::*;
+/// let ctx = AlignContext::new().unwrap();
 ///
 /// let xs = vec!["trash", "garbage", "content text", "second piece", "third part", "ignorable"];
 /// let ys = vec!["content water", "second thing", "third chunk"];
-/// let (got, _) = alignment_path(&xs, &ys, true, &ctx);
+/// let (got, _) = alignment_path(&xs, &ys, true, true, &ctx).unwrap();
 /// assert_eq!(got, vec![
 ///   (2, 0),
 ///   (3, 1),
@@ -56,15 +61,16 @@ pub fn alignment_path(
     xs: &Vec<&str>,
     ys: &Vec<&str>,
     flexible_start: bool,
+    flexible_end: bool,
     ctx: &AlignContext,
-) -> (Vec<(usize, usize)>, Vec<Vec<f32>>) {
+) -> AlignResult<WindowAlignment> {
     // first joining xs and ys into a vector
     // then computing similarity matrix: vector X vector
     // then reconstucting matrix into: xs X ys
     let mut joined: Vec<&str> = vec![];
     joined.extend(xs);
     joined.extend(ys);
-    let joined_similarity_matrix = ctx.similarity.get_many(&joined[..]);
+    let joined_similarity_matrix = ctx.similarity.get_many(&joined[..])?;
     let xs_to_ys = reconstruct(joined_similarity_matrix, xs.len());
 
     // similarity(-1..1) -> cost (0..1)
@@ -79,9 +85,12 @@ pub fn alignment_path(
     //
     let cost_matrix = row_col_blended_normalization(&cost_matrix);
 
-    let path = find_path(cost_matrix, flexible_start);
+    let path = find_path(cost_matrix, flexible_start, flexible_end).ok_or(AlignError::NoPath {
+        left_start: 0,
+        right_start: 0,
+    })?;
 
-    (path, xs_to_ys)
+    Ok((path, xs_to_ys))
 }
 
 /// Normalize each row of `matrix` to [0,1].
@@ -92,8 +101,8 @@ pub fn normalize_rows(matrix: &Vec<Vec<f32>>) -> Vec<Vec<f32>> {
     for row in matrix.iter() {
         // Find min and max in this row
         if let (Some(&row_min), Some(&row_max)) = (
-            row.iter().min_by(|a, b| a.partial_cmp(b).unwrap()),
-            row.iter().max_by(|a, b| a.partial_cmp(b).unwrap()),
+            row.iter().min_by(|a, b| a.total_cmp(b)),
+            row.iter().max_by(|a, b| a.total_cmp(b)),
         ) {
             let range = row_max - row_min;
             if range > 0.0 {
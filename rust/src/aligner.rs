@@ -0,0 +1,661 @@
+use crate::error::{AlignError, AlignResult};
+use crate::html::{block_indices, get_sequence, LRPair};
+use crate::state::{state_files, window_files};
+use serde::Serialize;
+use std::fs::File;
+use std::io::BufReader;
+
+#[cfg(feature = "model")]
+use {
+    crate::align::{alignment_path, AlignContext},
+    crate::anchors::next_anchor,
+    crate::confidence::path_similarity,
+    crate::matrix::{encode_matrix, load_matrix},
+    crate::split::split_into_sentences,
+    crate::state::{read_json_valid, write_atomic, write_json_atomic},
+    regex::Regex,
+    std::time::Instant,
+};
+
+/// Window size when none is given, same as the command line default.
+pub const DEFAULT_WINDOW_SIZE: usize = 300;
+
+/// Alignment of two texts window by window, options are set builder style.
+///
+/// ```no_run
+/// use make_parallel_text::*;
+///
+/// let alignment = Aligner::new()
+///     .window_size(200)
+///     .align("The first one. And the second.", "Первое. И второе.")?;
+/// for (left, right) in alignment.block_texts() {
+///     println!("{} | {}", left, right);
+/// }
+/// # Ok::<(), AlignError>(())
+/// ```
+///
+/// Model is loaded by every `align` and `realign` call, it is there with the `model` feature.
+#[cfg(feature = "model")]
+#[derive(Debug, Clone)]
+pub struct Aligner {
+    window_size: usize,
+    anchors: Vec<(usize, usize)>,
+    state_dir: Option<String>,
+    save_matrices: bool,
+}
+
+/// Progress of aligning and writing outputs, for showing it or passing it on as JSON lines.
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum AlignEvent {
+    Started {
+        left_sentences: usize,
+        right_sentences: usize,
+        window_size: usize,
+    },
+    IterationStarted {
+        iteration: usize,
+        left_start: usize,
+        right_start: usize,
+        left_size: usize,
+        right_size: usize,
+    },
+    IterationFinished {
+        iteration: usize,
+        /// path steps found in the window
+        steps: usize,
+        /// taken from the state dir of an earlier run
+        skipped: bool,
+        seconds: f64,
+        /// sentences up to the end of the path, of each side
+        left_covered: usize,
+        right_covered: usize,
+        /// mean similarity of pairs on the path, when computed in this run
+        mean_similarity: Option<f32>,
+    },
+    Finished {
+        iterations: usize,
+        steps: usize,
+        seconds: f64,
+    },
+    /// something worth telling that does not stop the work, like a state file computed again
+    Notice { message: String },
+}
+
+/// Aligned texts: sentences of both sides and the path going through them.
+#[derive(Debug, Clone)]
+pub struct Alignment {
+    pub left_sentences: Vec<String>,
+    pub right_sentences: Vec<String>,
+    /// `(left, right)` sentence index pairs, moving forward on both sides
+    pub path: Vec<(usize, usize)>,
+}
+
+#[cfg(feature = "model")]
+impl Default for Aligner {
+    fn default() -> Self {
+        Self {
+            window_size: DEFAULT_WINDOW_SIZE,
+            anchors: vec![],
+            state_dir: None,
+            save_matrices: true,
+        }
+    }
+}
+
+#[cfg(feature = "model")]
+impl Aligner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sentences of each side in a window, affects tolerance for mismatch between texts and speed.
+    pub fn window_size(mut self, window_size: usize) -> Self {
+        self.window_size = window_size;
+        self
+    }
+
+    /// Known `(left, right)` sentence correspondences the path has to go through, see `anchors_from_file`.
+    pub fn anchors(mut self, anchors: Vec<(usize, usize)>) -> Self {
+        self.anchors = anchors;
+        self
+    }
+
+    /// Dir to keep windows in, interrupted alignment continues from them.
+    ///
+    /// It is not checked against inputs here, `verify_state` does that.
+    pub fn state_dir(mut self, dir: impl Into<String>) -> Self {
+        self.state_dir = Some(dir.into());
+        self
+    }
+
+    /// Whether to keep similarity matrices of windows in the state dir, they are only needed for inspecting.
+    pub fn save_matrices(mut self, save_matrices: bool) -> Self {
+        self.save_matrices = save_matrices;
+        self
+    }
+
+    /// Splits texts into sentences and aligns them.
+    pub fn align(&self, left_text: &str, right_text: &str) -> AlignResult<Alignment> {
+        self.align_sentences(
+            split_into_sentences(left_text),
+            split_into_sentences(right_text),
+        )
+    }
+
+    /// Aligns sentences already split, anchors refer to their indices.
+    ///
+    /// Windows of half the size overlap, next one starts from the middle of the path found in the previous one.
+    /// With a state dir the path includes corrections made by `realign`.
+    pub fn align_sentences(
+        &self,
+        left_sentences: Vec<String>,
+        right_sentences: Vec<String>,
+    ) -> AlignResult<Alignment> {
+        self.align_sentences_with(left_sentences, right_sentences, &mut |_| {})
+    }
+
+    /// Same as `align_sentences`, telling `on_event` how it goes.
+    pub fn align_sentences_with(
+        &self,
+        left_sentences: Vec<String>,
+        right_sentences: Vec<String>,
+        on_event: &mut dyn FnMut(&AlignEvent),
+    ) -> AlignResult<Alignment> {
+        for (side, sentences) in [
+            ("left text", &left_sentences),
+            ("right text", &right_sentences),
+        ] {
+            if sentences.is_empty() {
+                return Err(AlignError::EmptyInput(side.to_string()));
+            }
+        }
+        let started = Instant::now();
+        on_event(&AlignEvent::Started {
+            left_sentences: left_sentences.len(),
+            right_sentences: right_sentences.len(),
+            window_size: self.window_size,
+        });
+        let ctx = AlignContext::new()?;
+
+        let mut iteration = 0;
+        let mut joined: Vec<(usize, usize)> = vec![];
+
+        // for iteration
+        let mut left_start: usize = 0;
+        let mut right_start: usize = 0;
+
+        // 4090 seem to fit 1000-1500, but dijkstra is too slow above 500
+        let score_batch = self.window_size;
+        // window at the same start made no progress, next one goes up to the anchor
+        let mut stalled = false;
+
+        loop {
+            // an anchor at the very start pins the first window there too
+            let flexible_start = iteration == 0 && !self.anchors.contains(&(0, 0));
+            let iteration_started = Instant::now();
+
+            let anchor = next_anchor(&self.anchors, left_start, right_start);
+            let (left_take, right_take, anchored_end) =
+                window_shape(anchor, (left_start, right_start), score_batch, stalled);
+            on_event(&AlignEvent::IterationStarted {
+                iteration,
+                left_start,
+                right_start,
+                left_size: left_take.min(left_sentences.len() - left_start),
+                right_size: right_take.min(right_sentences.len() - right_start),
+            });
+
+            let files = self.state_dir.as_ref().map(|dir| {
+                (
+                    format!(
+                        "{}/path-{}-{}-{}.json",
+                        dir, iteration, left_start, right_start
+                    ),
+                    format!(
+                        "{}/matrix-{}-{}-{}.bin",
+                        dir, iteration, left_start, right_start
+                    ),
+                )
+            });
+            // interrupted runs of older versions could leave truncated files behind,
+            // path has to be readable for iteration to count as done,
+            // matrix is optional (--no-matrices) but has to be readable if it is there
+            let existing = match &files {
+                Some((path_file_name, matrix_file_name)) => {
+                    let path = match read_json_valid::<Vec<(usize, usize)>>(path_file_name) {
+                        Err(AlignError::StateCorrupted { file, reason }) => {
+                            on_event(&AlignEvent::Notice {
+                                message: format!("{} is corrupted ({}), recomputing", file, reason),
+                            });
+                            None
+                        }
+                        path => path?,
+                    };
+                    path.filter(|path| !path.is_empty())
+                        .filter(|_| {
+                            let corrupted = std::fs::metadata(matrix_file_name).is_ok()
+                                && load_matrix(matrix_file_name).is_err();
+                            if corrupted {
+                                on_event(&AlignEvent::Notice {
+                                    message: format!(
+                                        "{} is corrupted, recomputing",
+                                        matrix_file_name
+                                    ),
+                                });
+                            }
+                            !corrupted
+                        })
+                        // same window start, but anchors could have changed its shape since
+                        .filter(|path| {
+                            path.iter().all(|&(l, r)| l < left_take && r < right_take)
+                                && (flexible_start || path.first() == Some(&(0, 0)))
+                                && (!anchored_end
+                                    || path.last() == Some(&(left_take - 1, right_take - 1)))
+                        })
+                }
+                None => None,
+            };
+
+            let skipped = existing.is_some();
+            let (path, mean_similarity) = if let Some(path) = existing {
+                (path, None)
+            } else {
+                // anything from this iteration on was computed for another chain of windows
+                if let Some(dir) = &self.state_dir {
+                    for name in discard_iterations_from(dir, iteration)? {
+                        on_event(&AlignEvent::Notice {
+                            message: format!("discarding stale {}", name),
+                        });
+                    }
+                }
+
+                let left_xs: Vec<&str> = left_sentences
+                    .iter()
+                    .skip(left_start)
+                    .take(left_take)
+                    .map(|s| s.as_str())
+                    .collect();
+                let right_xs: Vec<&str> = right_sentences
+                    .iter()
+                    .skip(right_start)
+                    .take(right_take)
+                    .map(|s| s.as_str())
+                    .collect();
+
+                let (path, similarity_matrix) =
+                    alignment_path(&left_xs, &right_xs, flexible_start, !anchored_end, &ctx)
+                        .map_err(|error| error.offset(left_start, right_start))?;
+
+                // path goes last, it marks the iteration as done
+                if let Some((path_file_name, matrix_file_name)) = &files {
+                    if self.save_matrices {
+                        write_atomic(matrix_file_name, encode_matrix(&similarity_matrix))?;
+                    }
+                    write_json_atomic(path_file_name, &path)?;
+                }
+                let mean_similarity = path_similarity(&path, &similarity_matrix);
+                (path, mean_similarity)
+            };
+
+            let at_end = path.last().is_some_and(|last| {
+                left_start + last.0 + 1 == left_sentences.len()
+                    || right_start + last.1 + 1 == right_sentences.len()
+            });
+            // next window would start at the same place: one side of the anchor is right
+            // at the start while the other is further than the window, so the path can not
+            // move before the window reaches the anchor
+            if !path.is_empty() && !at_end && !anchored_end && path[path.len() / 2] == (0, 0) {
+                if anchor.is_none() {
+                    return Err(AlignError::Stalled {
+                        left_start,
+                        right_start,
+                    });
+                }
+                stalled = true;
+                continue;
+            }
+            stalled = false;
+            joined.extend(path.iter().map(|&(l, r)| (l + left_start, r + right_start)));
+
+            // reached any border is exit condition
+            if let Some(last) = path.last() {
+                on_event(&AlignEvent::IterationFinished {
+                    iteration,
+                    steps: path.len(),
+                    skipped,
+                    seconds: iteration_started.elapsed().as_secs_f64(),
+                    left_covered: left_start + last.0 + 1,
+                    right_covered: right_start + last.1 + 1,
+                    mean_similarity,
+                });
+                if at_end {
+                    break;
+                }
+            } else {
+                return Err(AlignError::NoPath {
+                    left_start,
+                    right_start,
+                });
+            }
+
+            if let (true, Some((l, r))) = (anchored_end, anchor) {
+                // path up to the anchor is final, next window starts right at it
+                left_start = l;
+                right_start = r;
+            } else {
+                let mid = path.get(path.len() / 2).unwrap();
+
+                left_start += mid.0;
+                right_start += mid.1;
+            }
+
+            iteration += 1;
+        }
+
+        let path = match &self.state_dir {
+            Some(dir) => joined_path(dir)?,
+            None => remove_backtracks(&joined),
+        };
+        on_event(&AlignEvent::Finished {
+            iterations: iteration + 1,
+            steps: path.len(),
+            seconds: started.elapsed().as_secs_f64(),
+        });
+        Ok(Alignment {
+            left_sentences,
+            right_sentences,
+            path,
+        })
+    }
+
+    /// Re-aligns given inclusive ranges of sentences with both ends fixed, returns path of the region.
+    ///
+    /// With a state dir the region is stored as a correction on top of windows,
+    /// older corrections it overlaps are replaced, windows themselves stay as they are.
+    pub fn realign(
+        &self,
+        left_sentences: &[String],
+        right_sentences: &[String],
+        (left_from, left_to): (usize, usize),
+        (right_from, right_to): (usize, usize),
+    ) -> AlignResult<Vec<(usize, usize)>> {
+        for (side, from, to) in [
+            ("left", left_from, left_to),
+            ("right", right_from, right_to),
+        ] {
+            if from > to {
+                return Err(AlignError::ReversedRange {
+                    side: side.to_string(),
+                    from,
+                    to,
+                });
+            }
+        }
+        if left_to >= left_sentences.len() || right_to >= right_sentences.len() {
+            return Err(AlignError::OutOfRange {
+                left: left_sentences.len(),
+                right: right_sentences.len(),
+            });
+        }
+
+        let left_xs: Vec<&str> = left_sentences[left_from..=left_to]
+            .iter()
+            .map(|s| s.as_str())
+            .collect();
+        let right_xs: Vec<&str> = right_sentences[right_from..=right_to]
+            .iter()
+            .map(|s| s.as_str())
+            .collect();
+
+        let ctx = AlignContext::new()?;
+        let (path, _) = alignment_path(&left_xs, &right_xs, false, false, &ctx)
+            .map_err(|error| error.offset(left_from, right_from))?;
+        let path: Vec<(usize, usize)> = path
+            .into_iter()
+            .map(|(l, r)| (l + left_from, r + right_from))
+            .collect();
+        let Some(dir) = &self.state_dir else {
+            return Ok(path);
+        };
+
+        // overlapping older corrections are replaced by this one
+        for overlay in realigned_paths(dir)? {
+            if let (Some(&first), Some(&last)) = (overlay.first(), overlay.last()) {
+                let overlaps = first.0 <= left_to && left_from <= last.0
+                    || first.1 <= right_to && right_from <= last.1;
+                if overlaps {
+                    let file = realign_file_name(dir, first, last);
+                    std::fs::remove_file(&file).map_err(AlignError::io(&file))?;
+                }
+            }
+        }
+
+        let file = realign_file_name(dir, (left_from, right_from), (left_to, right_to));
+        write_json_atomic(&file, &path)?;
+
+        Ok(path)
+    }
+}
+
+/// Sizes of the window at `start` and whether its path has to end exactly at the anchor.
+///
+/// Window does not go past the next anchor, when the anchor fits into it the path ends there.
+/// After a window that made no progress (`stalled`) it goes up to the anchor on both sides,
+/// even when that is bigger than `window_size`.
+///
+/// ```
+/// use make_parallel_text::*;
+///
+/// assert_eq!(window_shape(None, (0, 0), 300, false), (300, 300, false));
+/// assert_eq!(window_shape(Some((100, 120)), (0, 0), 300, false), (101, 121, true));
+/// // left side of the anchor is at the start, right one is far away
+/// assert_eq!(window_shape(Some((5, 1000)), (5, 0), 300, false), (1, 300, false));
+/// assert_eq!(window_shape(Some((5, 1000)), (5, 0), 300, true), (1, 1001, true));
+/// ```
+pub fn window_shape(
+    anchor: Option<(usize, usize)>,
+    (left_start, right_start): (usize, usize),
+    window_size: usize,
+    stalled: bool,
+) -> (usize, usize, bool) {
+    match anchor {
+        Some((l, r)) if stalled => (l - left_start + 1, r - right_start + 1, true),
+        Some((l, r)) => (
+            window_size.min(l - left_start + 1),
+            window_size.min(r - right_start + 1),
+            l - left_start < window_size && r - right_start < window_size,
+        ),
+        None => (window_size, window_size, false),
+    }
+}
+
+impl Alignment {
+    /// Path grouped into blocks, a block is what outputs show as one row.
+    pub fn blocks(&self) -> Vec<Vec<LRPair>> {
+        get_sequence(&self.path)
+    }
+
+    /// Left and right text of every block, sentences joined by spaces.
+    pub fn block_texts(&self) -> Vec<(String, String)> {
+        let join = |indices: Vec<usize>, sentences: &[String]| {
+            indices
+                .iter()
+                .filter_map(|&i| sentences.get(i).map(|s| s.as_str()))
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+
+        self.blocks()
+            .iter()
+            .map(|block| {
+                let (left, right) = block_indices(block);
+                (
+                    join(left, &self.left_sentences),
+                    join(right, &self.right_sentences),
+                )
+            })
+            .collect()
+    }
+}
+
+/// Path of the whole texts from windows in the state dir, with `realign` corrections on top.
+pub fn joined_path(dir: &str) -> AlignResult<Vec<(usize, usize)>> {
+    let files = window_files(dir)?;
+
+    // This will store the final joined vector
+    let mut all_data: Vec<(usize, usize)> = Vec::new();
+
+    // Process each file in sorted order
+    for window in files {
+        let data = window.path()?;
+
+        // Apply the window start. If the item is (a, b), then the updated item is (a + left_start, b + right_start).
+        let updated_data = data
+            .into_iter()
+            .map(|(a, b)| (a + window.left_start, b + window.right_start))
+            .collect::<Vec<_>>();
+
+        // Push into the final collection
+        all_data.extend(updated_data);
+    }
+
+    // overlaps caused by half-window movement between each path found
+    let mut all_data = remove_backtracks(&all_data);
+
+    // manual corrections made by realign go on top
+    for overlay in realigned_paths(dir)? {
+        all_data = splice_path(&all_data, &overlay);
+    }
+
+    Ok(all_data)
+}
+
+#[cfg(feature = "model")]
+fn realign_file_name(dir: &str, from: (usize, usize), to: (usize, usize)) -> String {
+    format!(
+        "{}/realign-{}-{}-{}-{}.json",
+        dir, from.0, from.1, to.0, to.1
+    )
+}
+
+// Realigned paths with absolute indices, ordered by position in texts.
+fn realigned_paths(dir: &str) -> AlignResult<Vec<Vec<(usize, usize)>>> {
+    let mut paths = Vec::new();
+    for entry in state_files(dir, "realign-*-*-*-*.json")? {
+        let file = entry.to_string_lossy().to_string();
+        let reader = BufReader::new(File::open(&file).map_err(AlignError::io(&file))?);
+        let path: Vec<(usize, usize)> =
+            serde_json::from_reader(reader).map_err(|e| AlignError::StateCorrupted {
+                file,
+                reason: e.to_string(),
+            })?;
+        paths.push(path);
+    }
+    paths.sort_by_key(|path| path.first().copied());
+
+    Ok(paths)
+}
+
+/// Replaces part of the `path` covered by the `overlay` (from its first to its last point).
+///
+/// # Example
+///
+/// ```
+/// use make_parallel_text::*;
+///
+/// let path = vec![(0, 0), (1, 1), (2, 1), (3, 1), (4, 2), (5, 3)];
+/// let overlay = vec![(1, 1), (2, 2), (3, 2), (4, 2)];
+///
+/// let output = splice_path(&path, &overlay);
+/// assert_eq!(output, vec![(0, 0), (1, 1), (2, 2), (3, 2), (4, 2), (5, 3)]);
+/// ```
+pub fn splice_path(path: &[(usize, usize)], overlay: &[(usize, usize)]) -> Vec<(usize, usize)> {
+    let (Some(&first), Some(&last)) = (overlay.first(), overlay.last()) else {
+        return path.to_vec();
+    };
+
+    // path only moves forward on both sides, so whatever is not after the overlay start
+    // is the head and whatever is not before its end is the tail
+    let head = path
+        .iter()
+        .take_while(|&&(l, r)| l <= first.0 && r <= first.1 && (l, r) != first);
+    let tail = path
+        .iter()
+        .skip_while(|&&(l, r)| l < last.0 || r < last.1)
+        .filter(|&&p| p != last);
+
+    head.chain(overlay).chain(tail).copied().collect()
+}
+
+// Removes path and matrix files of given iteration and all later ones, returns their names.
+#[cfg(feature = "model")]
+fn discard_iterations_from(dir: &str, iteration: usize) -> AlignResult<Vec<String>> {
+    let re = Regex::new(r"(path|matrix)-(\d+)-\d+-\d+\.(json|bin)$").expect("valid pattern");
+    let mut discarded = vec![];
+
+    for kind in ["path-*-*-*.json", "matrix-*-*-*.json", "matrix-*-*-*.bin"] {
+        for file in state_files(dir, kind)? {
+            let name = file
+                .file_name()
+                .and_then(|s| s.to_str())
+                .unwrap_or_default();
+            if let Some(caps) = re.captures(name) {
+                // too big to parse is surely not earlier
+                if caps[2].parse::<usize>().map_or(true, |i| i >= iteration) {
+                    std::fs::remove_file(&file).map_err(AlignError::io(&file.to_string_lossy()))?;
+                    discarded.push(name.to_string());
+                }
+            }
+        }
+    }
+
+    Ok(discarded)
+}
+
+/// Removes branches in a path (represented by `(usize, usize)` coordinates) that
+/// were invalidated by backtracking.
+///
+/// # Example
+///
+/// ```
+/// use make_parallel_text::*;
+///
+/// // Suppose we have a path where (2,0) gets revisited, causing everything
+/// // after the *first* (2,0) to be truncated before continuing.
+/// let input = vec![
+///     (0,0),
+///     (1,0),
+///     (2,0),
+///     (3,0),
+///     (2,0), // backtrack to the point at index 2
+///     (2,1),
+///     (2,2),
+///     (2,0), // backtrack again to the point at index 2 (in the truncated path)
+///     (2,3)
+/// ];
+///
+/// let output = remove_backtracks(&input);
+/// assert_eq!(output, vec![
+///     (0,0),
+///     (1,0),
+///     (2,0),
+///     (2,3)  // all invalidated branches between the old (2,0) and this new (2,0) got removed
+/// ]);
+/// ```
+pub fn remove_backtracks(path: &[(usize, usize)]) -> Vec<(usize, usize)> {
+    let mut result = Vec::new();
+
+    for &p in path {
+        // Check if `p` already appears in `result`; if so, truncate everything
+        // after its *first* occurrence. This effectively discards the old path
+        // from that point forward, replacing it with the “new” path.
+        if let Some(i) = result.iter().position(|&q| q == p) {
+            result.truncate(i);
+        }
+        // Now push the current point onto our canonical path
+        result.push(p);
+    }
+
+    result
+}
@@ -0,0 +1,144 @@
+use anyhow::*;
+use serde::Deserialize;
+
+// One side of an anchor as given in the file:
+// either sentence index or beginning of the sentence text
+#[derive(Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum AnchorSide {
+    Index(usize),
+    Prefix(String),
+}
+
+impl AnchorSide {
+    fn parse(value: &str) -> Self {
+        match value.trim().parse::<usize>().ok() {
+            Some(index) => AnchorSide::Index(index),
+            None => AnchorSide::Prefix(value.trim().to_string()),
+        }
+    }
+
+    // prefixes are searched starting at `from` so repeated headers resolve in order
+    fn resolve(&self, sentences: &[String], from: usize) -> Option<usize> {
+        match self {
+            AnchorSide::Index(index) => Some(*index).filter(|&i| i < sentences.len()),
+            AnchorSide::Prefix(prefix) => {
+                // sentences are lowercased by the splitter
+                let prefix = prefix.trim().to_lowercase();
+                sentences
+                    .iter()
+                    .enumerate()
+                    .skip(from)
+                    .find(|(_, sentence)| sentence.starts_with(&prefix))
+                    .map(|(i, _)| i)
+            }
+        }
+    }
+}
+
+/// Known correspondences between left and right sentences.
+///
+/// File is either JSON: `[[1520, 1377], ["chapter xii", "глава xii"]]`,
+/// or TSV with a pair per line (`#` starts a comment).
+/// Each side is a sentence index or a beginning of sentence text.
+///
+/// Returns resolved `(left, right)` sentence indices, strictly increasing on both sides.
+///
+/// ```
+/// use make_parallel_text::*;
+///
+/// let left = split_into_sentences("Intro. Chapter one. Text. Chapter two. More.");
+/// let right = split_into_sentences("Вступление. Глава первая. Текст. Глава вторая. Ещё.");
+/// let dir = tempfile::tempdir()?;
+/// let file = dir.path().join("anchors.tsv");
+/// let file = file.to_str().unwrap();
+///
+/// std::fs::write(file, "# left\tright\n1\t1\n\nchapter two\tглава вторая\n")?;
+/// assert_eq!(anchors_from_file(file, &left, &right)?, vec![(1, 1), (3, 3)]);
+///
+/// std::fs::write(file, "1 1\n")?;
+/// let error = anchors_from_file(file, &left, &right).unwrap_err();
+/// assert!(error.to_string().contains("not tab separated"));
+///
+/// std::fs::write(file, "3\t3\nchapter one\tглава первая\n")?;
+/// let error = anchors_from_file(file, &left, &right).unwrap_err();
+/// assert!(error.to_string().contains("is not found"));
+///
+/// std::fs::write(file, "3\t3\n1\t1\n")?;
+/// let error = anchors_from_file(file, &left, &right).unwrap_err();
+/// assert!(error.to_string().contains("goes before previous anchor"));
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn anchors_from_file(
+    file: &str,
+    left_sentences: &[String],
+    right_sentences: &[String],
+) -> Result<Vec<(usize, usize)>> {
+    let text = std::fs::read_to_string(file)
+        .with_context(|| format!("can not read anchors file {}", file))?;
+
+    let sides: Vec<(AnchorSide, AnchorSide)> = if file.ends_with(".json") {
+        serde_json::from_str(&text).with_context(|| format!("bad anchors json {}", file))?
+    } else {
+        parse_tsv(&text)?
+    };
+
+    let mut anchors: Vec<(usize, usize)> = vec![];
+    for (left, right) in &sides {
+        let (left_from, right_from) = anchors.last().map_or((0, 0), |&(l, r)| (l + 1, r + 1));
+
+        let l = left.resolve(left_sentences, left_from);
+        let r = right.resolve(right_sentences, right_from);
+        let (l, r) = match (l, r) {
+            (Some(l), Some(r)) => (l, r),
+            _ => bail!("anchor {:?} - {:?} is not found in texts", left, right),
+        };
+
+        if l < left_from || r < right_from {
+            bail!(
+                "anchor {:?} - {:?} ({}, {}) goes before previous anchor",
+                left,
+                right,
+                l,
+                r
+            );
+        }
+        anchors.push((l, r));
+    }
+
+    Ok(anchors)
+}
+
+fn parse_tsv(text: &str) -> Result<Vec<(AnchorSide, AnchorSide)>> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !(line.is_empty() || line.starts_with('#')))
+        .map(|line| match line.split_once('\t') {
+            Some((left, right)) => Ok((AnchorSide::parse(left), AnchorSide::parse(right))),
+            None => bail!("anchor line is not tab separated: {}", line),
+        })
+        .collect()
+}
+
+/// Next anchor that is still ahead of the window start.
+///
+/// Anchor right at the start is not returned, the window starts there with the path pinned to it.
+///
+/// ```
+/// use make_parallel_text::*;
+///
+/// let anchors = vec![(10, 12), (20, 25)];
+/// assert_eq!(next_anchor(&anchors, 0, 0), Some((10, 12)));
+/// assert_eq!(next_anchor(&anchors, 10, 12), Some((20, 25)));
+/// assert_eq!(next_anchor(&anchors, 20, 25), None);
+/// ```
+pub fn next_anchor(
+    anchors: &[(usize, usize)],
+    left_start: usize,
+    right_start: usize,
+) -> Option<(usize, usize)> {
+    anchors
+        .iter()
+        .find(|&&(l, r)| l >= left_start && r >= right_start && (l, r) != (left_start, right_start))
+        .copied()
+}
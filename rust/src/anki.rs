@@ -0,0 +1,92 @@
+use crate::export::ExportBlock;
+use crate::options::{OutputOptions, Side};
+use crate::stream::{Block, BlockWriter};
+use anyhow::*;
+use std::io::Write;
+
+/// Anki import file: a Basic note per short block, tagged with its book and chapter.
+///
+/// Front is `--anki-front` side, back is the other one. `chapters` are block indices
+/// where chapters start, blocks before the first one get `chapter-0`.
+pub struct AnkiWriter<'a, W: Write> {
+    out: W,
+    chapters: &'a [usize],
+    args: &'a OutputOptions,
+    deck: &'a str,
+}
+
+impl<'a, W: Write> AnkiWriter<'a, W> {
+    pub fn new(out: W, chapters: &'a [usize], args: &'a OutputOptions, deck: &'a str) -> Self {
+        Self {
+            out,
+            chapters,
+            args,
+            deck,
+        }
+    }
+}
+
+impl<W: Write> BlockWriter for AnkiWriter<'_, W> {
+    fn begin(&mut self) -> Result<()> {
+        // file headers are understood by Anki 2.1.54+
+        write!(
+            self.out,
+            "#separator:tab\n#html:false\n#notetype:Basic\n#deck:{}\n#tags column:3\n",
+            self.deck.replace(['\t', '\n', '\r'], " ")
+        )?;
+        Ok(())
+    }
+
+    fn block(&mut self, block: &Block) -> Result<()> {
+        let args = self.args;
+        let sentences = block.left.len().max(block.right.len());
+        if sentences > args.anki_max_sentences {
+            return Ok(());
+        }
+        let Some(block) = ExportBlock::from_block(block, args) else {
+            return Ok(());
+        };
+        let (front, back) = match args.anki_front {
+            Side::Left => (&block.left_text, &block.right_text),
+            Side::Right => (&block.right_text, &block.left_text),
+        };
+        if front.chars().count() > args.anki_max_chars || back.chars().count() > args.anki_max_chars
+        {
+            return Ok(());
+        }
+
+        let chapter = self.chapters.partition_point(|&start| start <= block.block);
+        writeln!(
+            self.out,
+            "{}\t{}\t{} chapter-{}",
+            field(front),
+            field(back),
+            tag(self.deck),
+            chapter
+        )?;
+        Ok(())
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+// Anki reads fields with quotes as quoted ones, so those are quoted properly.
+fn field(text: &str) -> String {
+    let text = text.replace(['\t', '\n', '\r'], " ");
+    if text.contains('"') {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text
+    }
+}
+
+// tags are separated by spaces
+fn tag(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join("_")
+        .to_lowercase()
+}
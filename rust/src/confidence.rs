@@ -0,0 +1,72 @@
+use crate::html::LRPair;
+use crate::matrix::load_matrix;
+use crate::state::window_files;
+use anyhow::*;
+use std::collections::HashMap;
+
+/// Similarity of sentence pairs of the joined path, taken from saved window matrices.
+///
+/// Sentences are embedded one by one, so similarity of a pair is the same in any window containing it.
+/// Pairs outside of saved matrices (`--no-matrices`) have no value.
+pub struct Similarities {
+    values: HashMap<(usize, usize), f32>,
+}
+
+impl Similarities {
+    pub fn from_state(dir: &str, path: &[(usize, usize)]) -> Result<Self> {
+        let mut values = HashMap::new();
+
+        for window in window_files(dir)? {
+            let Some(matrix_file) = window.matrix_file() else {
+                continue;
+            };
+            let matrix = load_matrix(&matrix_file)?;
+            let rows = matrix.len();
+            let cols = matrix.first().map_or(0, |row| row.len());
+
+            for &(l, r) in path {
+                let (Some(x), Some(y)) = (
+                    l.checked_sub(window.left_start),
+                    r.checked_sub(window.right_start),
+                ) else {
+                    continue;
+                };
+                if x < cols && y < rows {
+                    values.insert((l, r), matrix[y][x]);
+                }
+            }
+        }
+
+        Ok(Self { values })
+    }
+
+    pub fn get(&self, l: usize, r: usize) -> Option<f32> {
+        self.values.get(&(l, r)).copied()
+    }
+
+    /// Confidence of a block from `get_sequence`: mean similarity of its pairs (-1..1).
+    pub fn block(&self, block: &[LRPair]) -> Option<f32> {
+        mean(block.iter().filter_map(|lr| self.get(lr.l, lr.r)))
+    }
+}
+
+/// Mean similarity of a window path in the matrix of that window.
+///
+/// Matrix rows are right sentences, as `alignment_path` makes it.
+pub fn path_similarity(path: &[(usize, usize)], matrix: &[Vec<f32>]) -> Option<f32> {
+    mean(
+        path.iter()
+            .filter_map(|&(l, r)| matrix.get(r).and_then(|row| row.get(l)).copied()),
+    )
+}
+
+/// Mean of the values, none when there are none.
+pub fn mean(values: impl IntoIterator<Item = f32>) -> Option<f32> {
+    let (sum, count) = values
+        .into_iter()
+        .fold((0., 0), |(sum, count), value| (sum + value, count + 1));
+    if count == 0 {
+        return None;
+    }
+    Some(sum / count as f32)
+}
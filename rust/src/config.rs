@@ -0,0 +1,423 @@
+use crate::context::Cli;
+use anyhow::*;
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory};
+use make_parallel_text::write_atomic;
+use std::any::TypeId;
+use std::collections::HashSet;
+
+/// Settings of the last run, kept in the state dir.
+pub const RUN_FILE: &str = "run.toml";
+
+// options that only make sense for a single run, they are not persisted
+const NOT_PERSISTED: &[&str] = &[
+    "config",
+    "force-restart",
+    "stdout",
+    "events",
+    "left-range",
+    "right-range",
+];
+
+// commands whose settings are persisted: the ones that write outputs into the state dir
+const PERSISTED_COMMANDS: &[&str] = &["align", "realign", "render"];
+
+// [left] and [right] tables of config: "file" is the text itself, other keys are options
+// with the side prefix, like `title` of [left] is --left-title
+fn side_option(side: &str, key: &str) -> String {
+    if key == "file" {
+        side.to_string()
+    } else {
+        format!("{}-{}", side, key)
+    }
+}
+
+/// Command line with settings of the config file added before own arguments of the command,
+/// options given on the command line take precedence.
+///
+/// Config file is `--config FILE` or, without it, `run.toml` of the `--context` dir when it is there.
+/// Keys are long option names, like `font-size = "14pt"` or `column-widths = [3, 3, 1]`,
+/// options of a side can go into `[left]` and `[right]` tables. Settings the command does not
+/// have are skipped, so one file serves all commands.
+pub fn arguments_with_config(args: Vec<String>) -> Result<Vec<String>> {
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        return Ok(args);
+    }
+
+    let cli = Cli::command();
+    let (command, position) = match subcommand_position(&cli, &args) {
+        Some((command, index)) => (command, index + 1),
+        None => (&cli, 1),
+    };
+    let own = &args[position.min(args.len())..];
+
+    // --config is global, it can come before the command
+    let file = match option_value(&args[1.min(args.len())..], "config", None) {
+        Some(file) => file,
+        None => match option_value(own, "context", Some('c')) {
+            Some(dir) if std::fs::metadata(format!("{}/{}", dir, RUN_FILE)).is_ok() => {
+                format!("{}/{}", dir, RUN_FILE)
+            }
+            _ => return Ok(args),
+        },
+    };
+    let text =
+        std::fs::read_to_string(&file).with_context(|| format!("can not read config {}", file))?;
+    let table: toml::Table =
+        toml::from_str(&text).with_context(|| format!("can not parse config {}", file))?;
+
+    let mut settings: Vec<(String, toml::Value)> = vec![];
+    for (key, value) in table {
+        match (key.as_str(), value) {
+            (side @ ("left" | "right"), toml::Value::Table(side_table)) => {
+                for (key, value) in side_table {
+                    settings.push((side_option(side, &key.replace('_', "-")), value));
+                }
+            }
+            (_, value) => settings.push((key.replace('_', "-"), value)),
+        }
+    }
+
+    let known: HashSet<&str> = cli
+        .get_arguments()
+        .chain(cli.get_subcommands().flat_map(|c| c.get_arguments()))
+        .filter_map(|arg| arg.get_long())
+        .collect();
+    let given = given_options(command, own);
+
+    let mut added: Vec<String> = vec![];
+    for (option, value) in settings {
+        if !known.contains(option.as_str()) {
+            bail!("unknown setting {} in {}", option, file);
+        }
+        let Some(arg) = command
+            .get_arguments()
+            .find(|arg| arg.get_long() == Some(option.as_str()))
+        else {
+            continue;
+        };
+        if option == "config" || given.contains(&option) {
+            continue;
+        }
+
+        let flag = format!("--{}", option);
+        match value {
+            toml::Value::Boolean(on) if !arg.get_action().takes_values() => {
+                if on {
+                    added.push(flag);
+                }
+            }
+            toml::Value::Array(values) => {
+                let values: Vec<String> = values.iter().map(value_text).collect::<Result<_>>()?;
+                added.push(flag);
+                added.push(values.join(","));
+            }
+            value => {
+                added.push(flag);
+                added.push(value_text(&value)?);
+            }
+        }
+    }
+
+    let mut args = args;
+    let position = position.min(args.len());
+    args.splice(position..position, added);
+    Ok(args)
+}
+
+fn value_text(value: &toml::Value) -> Result<String> {
+    Ok(match value {
+        toml::Value::String(text) => text.clone(),
+        toml::Value::Integer(n) => n.to_string(),
+        toml::Value::Float(x) => x.to_string(),
+        toml::Value::Boolean(b) => b.to_string(),
+        _ => bail!("setting value {} is not a string, number or list", value),
+    })
+}
+
+// Subcommand in raw arguments and its index, options before it (like --config) are skipped.
+fn subcommand_position<'a>(
+    cli: &'a clap::Command,
+    args: &[String],
+) -> Option<(&'a clap::Command, usize)> {
+    let mut index = 1;
+    while let Some(arg) = args.get(index) {
+        if arg == "--" {
+            return None;
+        }
+        if let Some(command) = cli.find_subcommand(arg) {
+            return Some((command, index));
+        }
+        let option = match (arg.strip_prefix("--"), arg.strip_prefix('-')) {
+            (Some(long), _) => cli.get_arguments().find(|a| a.get_long() == Some(long)),
+            (None, Some(short)) if short.chars().count() == 1 => cli
+                .get_arguments()
+                .find(|a| a.get_short() == short.chars().next()),
+            _ => None,
+        };
+        let takes_value = option.is_some_and(|a| a.get_action().takes_values());
+        index += if takes_value { 2 } else { 1 };
+    }
+    None
+}
+
+// Value of an option in raw arguments: `--name VALUE`, `--name=VALUE`, `-s VALUE` or `-sVALUE`.
+fn option_value(args: &[String], long: &str, short: Option<char>) -> Option<String> {
+    let long_flag = format!("--{}", long);
+    let short_flag = short.map(|c| format!("-{}", c));
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            break;
+        }
+        if let Some(value) = arg.strip_prefix(&format!("{}=", long_flag)) {
+            return Some(value.to_string());
+        }
+        if *arg == long_flag || Some(arg) == short_flag.as_ref() {
+            return args.next().cloned();
+        }
+        if let Some(value) = short_flag
+            .as_ref()
+            .and_then(|flag| arg.strip_prefix(flag.as_str()))
+        {
+            return Some(value.trim_start_matches('=').to_string());
+        }
+    }
+    None
+}
+
+// Flag turning off the given one or turned off by it: `swap-sides` and `no-swap-sides`.
+fn opposite_flag<'a>(command: &'a clap::Command, long: &str) -> Option<&'a str> {
+    let opposite = match long.strip_prefix("no-") {
+        Some(flag) => flag.to_string(),
+        None => format!("no-{}", long),
+    };
+    command
+        .get_arguments()
+        .filter(|arg| !arg.get_action().takes_values())
+        .find_map(|arg| arg.get_long().filter(|&other| other == opposite))
+}
+
+// Long names of options present in raw arguments of a command, a flag counts for its opposite too.
+// Short options can be grouped and have the value attached: `-fw300`.
+fn given_options(command: &clap::Command, args: &[String]) -> HashSet<String> {
+    let mut given = HashSet::new();
+    for arg in args.iter().take_while(|arg| *arg != "--") {
+        if let Some(long) = arg.strip_prefix("--") {
+            given.insert(long.split('=').next().unwrap_or(long).to_string());
+        } else if let Some(shorts) = arg.strip_prefix('-') {
+            for short in shorts.chars() {
+                let Some(option) = command
+                    .get_arguments()
+                    .find(|a| a.get_short() == Some(short))
+                else {
+                    break;
+                };
+                if let Some(long) = option.get_long() {
+                    given.insert(long.to_string());
+                }
+                // rest of the argument is its value
+                if option.get_action().takes_values() {
+                    break;
+                }
+            }
+        }
+    }
+    let opposites: Vec<&str> = given
+        .iter()
+        .filter_map(|long| opposite_flag(command, long))
+        .collect();
+    given.extend(opposites.into_iter().map(String::from));
+    given
+}
+
+/// Writes options given on the command line, directly or by config, into `run.toml` of the state dir.
+///
+/// Settings of other commands already in the file are kept. Defaults are not written,
+/// so a run with the file gets new defaults of a newer version.
+pub fn persist_settings(matches: &ArgMatches) -> Result<()> {
+    let cli = Cli::command();
+    let (command, matches) = match matches.subcommand() {
+        Some((name, sub_matches)) if PERSISTED_COMMANDS.contains(&name) => {
+            (cli.find_subcommand(name).unwrap_or(&cli), sub_matches)
+        }
+        Some(_) => return Ok(()),
+        None => (&cli, matches),
+    };
+    let Some(dir) = matches
+        .get_raw("context")
+        .and_then(|mut values| values.next())
+    else {
+        return Ok(());
+    };
+    let dir = dir.to_string_lossy().to_string();
+    let file = format!("{}/{}", dir, RUN_FILE);
+
+    let mut table: toml::Table = match std::fs::read_to_string(&file).ok() {
+        Some(text) => {
+            toml::from_str(&text).with_context(|| format!("can not parse config {}", file))?
+        }
+        None => toml::Table::new(),
+    };
+    let mut sides = ["left", "right"].map(|side| match table.remove(side) {
+        Some(toml::Value::Table(side_table)) => (side, side_table),
+        _ => (side, toml::Table::new()),
+    });
+    for arg in command.get_arguments() {
+        let id = arg.get_id().as_str();
+        let Some(long) = arg.get_long() else {
+            continue;
+        };
+        if NOT_PERSISTED.contains(&long)
+            || matches.value_source(id) != Some(ValueSource::CommandLine)
+        {
+            continue;
+        }
+
+        // flag replaces its opposite set by an earlier run
+        if let Some(opposite) = opposite_flag(command, long) {
+            table.remove(opposite);
+        }
+        let text = arg.get_value_parser().type_id() == TypeId::of::<String>();
+        let value = if arg.get_action().takes_values() {
+            let values: Vec<toml::Value> = matches
+                .get_raw(id)
+                .into_iter()
+                .flatten()
+                .map(|value| {
+                    let value = value.to_string_lossy();
+                    if text {
+                        toml::Value::String(value.to_string())
+                    } else {
+                        typed_value(&value)
+                    }
+                })
+                .collect();
+            if arg.get_value_delimiter().is_some() {
+                toml::Value::Array(values)
+            } else {
+                match values.into_iter().next() {
+                    Some(value) => value,
+                    None => continue,
+                }
+            }
+        } else {
+            toml::Value::Boolean(true)
+        };
+
+        match sides
+            .iter_mut()
+            .find(|(side, _)| long == *side || long.starts_with(&format!("{}-", side)))
+        {
+            Some((side, side_table)) => {
+                let key = long
+                    .strip_prefix(*side)
+                    .unwrap_or(long)
+                    .trim_start_matches('-');
+                let key = if key.is_empty() { "file" } else { key };
+                side_table.insert(key.to_string(), value);
+            }
+            None => {
+                table.insert(long.to_string(), value);
+            }
+        }
+    }
+    for (side, side_table) in sides {
+        if !side_table.is_empty() {
+            table.insert(side.to_string(), toml::Value::Table(side_table));
+        }
+    }
+
+    std::fs::create_dir_all(&dir)?;
+    let text = format!(
+        "# settings of runs in this dir, used by following ones unless given on the command line\n{}",
+        toml::to_string(&table)?
+    );
+    write_atomic(&file, text)?;
+    Ok(())
+}
+
+// Values of number options are kept as numbers in the file, anything else as text.
+fn typed_value(text: &str) -> toml::Value {
+    let integer = text.parse::<i64>().ok().map(toml::Value::Integer);
+    let float = || {
+        text.parse::<f64>()
+            .ok()
+            .filter(|_| text.contains('.'))
+            .map(toml::Value::Float)
+    };
+    integer
+        .or_else(float)
+        .unwrap_or_else(|| toml::Value::String(text.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(dir: &str, own: &[&str]) -> Vec<String> {
+        [
+            "make-parallel-text",
+            "render",
+            "-l",
+            "A.txt",
+            "-r",
+            "B.txt",
+            "-c",
+            dir,
+        ]
+        .iter()
+        .chain(own)
+        .map(|arg| arg.to_string())
+        .collect()
+    }
+
+    #[test]
+    fn command_line_takes_precedence_over_config() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let context = dir.path().to_string_lossy().to_string();
+        std::fs::write(
+            dir.path().join(RUN_FILE),
+            "font-size = \"14pt\"\nswap-sides = true\n[left]\ntitle = \"Moby Dick\"\n",
+        )?;
+
+        let given = args(&context, &["--font-size", "20pt", "--no-swap-sides"]);
+        let merged = arguments_with_config(given.clone())?;
+        assert_eq!(merged[..2], given[..2]);
+        assert_eq!(merged[2..4], ["--left-title", "Moby Dick"]);
+        assert_eq!(merged[4..], given[2..]);
+        Ok(())
+    }
+
+    #[test]
+    fn settings_of_a_run_are_kept_in_state_dir() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let context = dir.path().to_string_lossy().to_string();
+        std::fs::write(
+            dir.path().join(RUN_FILE),
+            "swap-sides = true\nfont-size = \"14pt\"\n",
+        )?;
+
+        let given = args(
+            &context,
+            &["--no-swap-sides", "--formats", "tmx,csv", "--stdout"],
+        );
+        persist_settings(&Cli::command().try_get_matches_from(given)?)?;
+
+        let text = std::fs::read_to_string(dir.path().join(RUN_FILE))?;
+        let table: toml::Table = toml::from_str(&text)?;
+        assert_eq!(table["no-swap-sides"], toml::Value::Boolean(true));
+        assert!(!table.contains_key("swap-sides"));
+        assert!(!table.contains_key("stdout"));
+        assert_eq!(table["font-size"], toml::Value::String("14pt".to_string()));
+        assert_eq!(
+            table["formats"],
+            toml::Value::Array(vec!["tmx".into(), "csv".into()])
+        );
+        assert_eq!(
+            table["left"]["file"],
+            toml::Value::String("A.txt".to_string())
+        );
+        Ok(())
+    }
+}
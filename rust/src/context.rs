@@ -1,7 +1,57 @@
-use clap::Parser;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use make_parallel_text::{
+    Colormap, ColumnKind, Format, Inputs, OutputOptions, Separator, Side, DEFAULT_CHAPTER_PATTERN,
+};
 
 /// Align two texts on semantical similarity across 10+ languages
 #[derive(Parser, Debug)]
+#[command(args_conflicts_with_subcommands = true)]
+#[cfg_attr(not(feature = "model"), command(subcommand_required = true))]
+pub struct Cli {
+    /// aligning itself when no command given
+    #[cfg(feature = "model")]
+    #[command(flatten)]
+    pub context: Option<Context>,
+
+    // kept out of Context: clap can not tell whether optional flattened Context
+    // was given when it has flattened args of its own
+    #[cfg(feature = "model")]
+    #[command(flatten)]
+    pub output: OutputArgs,
+
+    /// TOML file with settings, same names as long options; run.toml of the state dir is used without it
+    #[arg(long, global = true)]
+    pub config: Option<String>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Align two texts and write outputs, same as giving its arguments without a command
+    #[cfg(feature = "model")]
+    Align(Box<Align>),
+
+    /// Write outputs again from existing state dir, without loading the model
+    Render(Box<Render>),
+
+    /// Write only given outputs from existing state dir, without loading the model
+    Export(Box<Export>),
+
+    /// Print statistics of existing alignment
+    Inspect(Inspect),
+
+    /// Re-align only given ranges of sentences and splice it into the existing result
+    #[cfg(feature = "model")]
+    Realign(Box<Realign>),
+
+    /// Render similarity matrix and found path of every window into images in the state dir
+    RenderLog(RenderLog),
+}
+
+#[cfg(feature = "model")]
+#[derive(Args, Debug)]
 pub struct Context {
     /// left text file
     #[arg(short, long)]
@@ -18,7 +68,308 @@ pub struct Context {
     /// window size of alignment, affects tolerance for mismatch between files, strongly affects speed of alignment
     #[arg(short, long, default_value = "300")]
     pub window_size: usize,
+
+    /// file with known sentence correspondences that path has to go through, TSV or JSON pairs of indices or sentence beginnings
+    #[arg(short, long)]
+    pub anchors: Option<String>,
+
+    /// discard state dir content made for other inputs or settings instead of refusing to run
+    #[arg(long)]
+    pub force_restart: bool,
+
+    /// do not save similarity matrices of windows, they are only needed for inspecting alignment
+    #[arg(long, overrides_with = "matrices")]
+    pub no_matrices: bool,
+
+    /// save similarity matrices, undoes --no-matrices of config or earlier runs
+    #[arg(long, overrides_with = "no_matrices")]
+    pub matrices: bool,
+
+    /// also write progress of aligning to standard output as JSON lines, one per event
+    #[arg(long, value_enum, conflicts_with = "stdout")]
+    pub events: Option<Events>,
     //
     // TODO: boolean flags on how to split inputs
     //
 }
+
+#[cfg(feature = "model")]
+impl Context {
+    pub fn inputs(&self) -> Inputs {
+        Inputs {
+            left: self.left.clone(),
+            right: self.right.clone(),
+            context: self.context.clone(),
+        }
+    }
+}
+
+/// Texts and state dir of an existing alignment
+#[derive(Args, Debug, Clone)]
+pub struct InputArgs {
+    /// left text file
+    #[arg(short, long)]
+    pub left: String,
+
+    /// right text file
+    #[arg(short, long)]
+    pub right: String,
+
+    /// dir with state of alignment process, outputs are written there too
+    #[arg(short, long)]
+    pub context: String,
+}
+
+impl InputArgs {
+    pub fn inputs(&self) -> Inputs {
+        Inputs {
+            left: self.left.clone(),
+            right: self.right.clone(),
+            context: self.context.clone(),
+        }
+    }
+}
+
+/// Look and metadata of output files
+#[derive(Args, Debug, Clone)]
+pub struct OutputArgs {
+    /// outputs to write, 3-columns, 2-columns, 1-column, overview and report by default
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub formats: Vec<Format>,
+
+    /// write the only output of --formats to standard output instead of a file, messages go to standard error
+    #[arg(long)]
+    pub stdout: bool,
+
+    /// keep italics and bold tags of input text (<i>, <b>, <em>, <strong>) in html, otherwise any markup is shown as text
+    #[arg(long, overrides_with = "no_inline_markup")]
+    pub inline_markup: bool,
+
+    /// undoes --inline-markup of config or earlier runs
+    #[arg(long, overrides_with = "inline_markup")]
+    pub no_inline_markup: bool,
+
+    /// CSS page size, for example "A5" or "12in 9in" [default: "A4 landscape" for columns, "A4" for one column]
+    #[arg(long)]
+    pub page_size: Option<String>,
+
+    #[arg(long, default_value = "Arial, sans-serif")]
+    pub font_family: String,
+
+    #[arg(long, default_value = "18pt")]
+    pub font_size: String,
+
+    /// relative widths of text and notes columns in their order, index and similarity columns take as little as they need
+    #[arg(long, value_delimiter = ',', default_value = "1,1,1")]
+    pub column_widths: Vec<u32>,
+
+    /// put right text first: into the left column, on top in one column layout
+    #[arg(long, overrides_with = "no_swap_sides")]
+    pub swap_sides: bool,
+
+    /// undoes --swap-sides of config or earlier runs
+    #[arg(long, overrides_with = "swap_sides")]
+    pub no_swap_sides: bool,
+
+    /// columns of additional custom-columns.html in their order, for example "index,right,left,similarity"
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub columns: Vec<ColumnKind>,
+
+    /// what one column layout shows of each block, top to bottom
+    #[arg(long, value_enum, value_delimiter = ',', default_value = "right,left")]
+    pub stack_order: Vec<ColumnKind>,
+
+    /// what interlinear layout shows of each block: main text first, then smaller lines under it
+    #[arg(long, value_enum, value_delimiter = ',', default_value = "left,right")]
+    pub interlinear_order: Vec<ColumnKind>,
+
+    /// let interlinear blocks run one after another like words of a paragraph instead of one under another
+    #[arg(long, overrides_with = "no_interlinear_flow")]
+    pub interlinear_flow: bool,
+
+    /// undoes --interlinear-flow of config or earlier runs
+    #[arg(long, overrides_with = "interlinear_flow")]
+    pub no_interlinear_flow: bool,
+
+    /// what goes between aligned blocks
+    #[arg(long, value_enum, default_value = "line")]
+    pub separator: Separator,
+
+    /// html file to use instead of built-in one, {{css}} in it is replaced with style and {{content}} with aligned text
+    #[arg(long)]
+    pub template: Option<String>,
+
+    /// css file added after built-in style, so its rules take precedence
+    #[arg(long)]
+    pub css: Option<String>,
+
+    /// title of left text in ebook metadata [default: left file name]
+    #[arg(long)]
+    pub left_title: Option<String>,
+
+    /// title of right text in ebook metadata [default: right file name]
+    #[arg(long)]
+    pub right_title: Option<String>,
+
+    /// language of left text, BCP 47 code like "en" or "ru", "und" is detected from the text
+    #[arg(long, default_value = "und")]
+    pub left_lang: String,
+
+    /// language of right text, BCP 47 code like "en" or "ru", "und" is detected from the text
+    #[arg(long, default_value = "und")]
+    pub right_lang: String,
+
+    /// TrueType font to embed into pdf, it has to cover characters of both texts [default: DejaVu Sans]
+    #[arg(long)]
+    pub font: Option<String>,
+
+    /// leave blocks with lower mean similarity (-1..1) out of translation memory, table and anki exports, it needs saved matrices
+    #[arg(long)]
+    pub min_similarity: Option<f32>,
+
+    /// side of anki cards front, the language being learned
+    #[arg(long, value_enum, default_value = "left")]
+    pub anki_front: Side,
+
+    /// longest block for anki cards, in sentences of either side
+    #[arg(long, default_value = "2")]
+    pub anki_max_sentences: usize,
+
+    /// longest text of anki card side, in characters
+    #[arg(long, default_value = "200")]
+    pub anki_max_chars: usize,
+
+    /// regex for sentences that start a chapter in ebook and anki tags (sentences are lowercase)
+    #[arg(long, default_value = DEFAULT_CHAPTER_PATTERN)]
+    pub chapter_pattern: String,
+}
+
+impl OutputArgs {
+    pub fn options(&self) -> OutputOptions {
+        OutputOptions {
+            formats: self.formats.clone(),
+            stdout: self.stdout,
+            inline_markup: self.inline_markup,
+            page_size: self.page_size.clone(),
+            font_family: self.font_family.clone(),
+            font_size: self.font_size.clone(),
+            column_widths: self.column_widths.clone(),
+            swap_sides: self.swap_sides,
+            columns: self.columns.clone(),
+            stack_order: self.stack_order.clone(),
+            interlinear_order: self.interlinear_order.clone(),
+            interlinear_flow: self.interlinear_flow,
+            separator: self.separator,
+            template: self.template.clone(),
+            css: self.css.clone(),
+            left_title: self.left_title.clone(),
+            right_title: self.right_title.clone(),
+            left_lang: self.left_lang.clone(),
+            right_lang: self.right_lang.clone(),
+            font: self.font.clone(),
+            min_similarity: self.min_similarity,
+            anki_front: self.anki_front,
+            anki_max_sentences: self.anki_max_sentences,
+            anki_max_chars: self.anki_max_chars,
+            chapter_pattern: self.chapter_pattern.clone(),
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Events {
+    /// event name in "event" field: started, iteration_started, iteration_finished, finished
+    Json,
+}
+
+#[cfg(feature = "model")]
+#[derive(Args, Debug)]
+pub struct Align {
+    #[command(flatten)]
+    pub context: Context,
+
+    #[command(flatten)]
+    pub output: OutputArgs,
+}
+
+#[derive(Args, Debug)]
+pub struct Render {
+    #[command(flatten)]
+    pub inputs: InputArgs,
+
+    #[command(flatten)]
+    pub output: OutputArgs,
+}
+
+#[derive(Args, Debug)]
+#[command(mut_arg("formats", |arg| {
+    arg.required(true)
+        .visible_alias("format")
+        .help("outputs to write, like \"tmx\" or \"tsv,csv\"")
+}))]
+pub struct Export {
+    #[command(flatten)]
+    pub inputs: InputArgs,
+
+    #[command(flatten)]
+    pub output: OutputArgs,
+}
+
+#[derive(Args, Debug)]
+pub struct Inspect {
+    #[command(flatten)]
+    pub inputs: InputArgs,
+}
+
+#[cfg(feature = "model")]
+#[derive(Args, Debug)]
+pub struct Realign {
+    #[command(flatten)]
+    pub inputs: InputArgs,
+
+    /// window size the alignment was made with, state dir made with another one is refused
+    #[arg(short, long, default_value = "300")]
+    pub window_size: usize,
+
+    #[command(flatten)]
+    pub output: OutputArgs,
+
+    /// left sentences to re-align, inclusive indices: FROM-TO
+    #[arg(long, value_parser = parse_range)]
+    pub left_range: (usize, usize),
+
+    /// right sentences to re-align, inclusive indices: FROM-TO
+    #[arg(long, value_parser = parse_range)]
+    pub right_range: (usize, usize),
+}
+
+#[cfg(feature = "model")]
+fn parse_range(value: &str) -> Result<(usize, usize), String> {
+    let (from, to) = value
+        .split_once('-')
+        .ok_or_else(|| format!("expected FROM-TO, got {}", value))?;
+    let from = from.trim().parse::<usize>().map_err(|e| e.to_string())?;
+    let to = to.trim().parse::<usize>().map_err(|e| e.to_string())?;
+    if from > to {
+        return Err(format!("range {} goes backwards", value));
+    }
+    Ok((from, to))
+}
+
+#[derive(Args, Debug)]
+pub struct RenderLog {
+    /// dir with state of alignment process, images are written there too
+    #[arg(short, long)]
+    pub context: String,
+
+    /// pixels per matrix cell
+    #[arg(short, long, default_value = "1")]
+    pub scale: u32,
+
+    #[arg(long, value_enum, default_value = "gray")]
+    pub colormap: Colormap,
+
+    /// render again windows that already have images
+    #[arg(short, long)]
+    pub force: bool,
+}
@@ -0,0 +1,476 @@
+use crate::html::{
+    block_indices, column_percents, columns_block_html, escape_html, separator_css, LRPair, Layout,
+};
+use crate::language::direction;
+use crate::manifest::file_hash;
+use crate::options::{ColumnKind, Inputs, OutputOptions};
+use crate::stream::{Block, BlockWriter};
+use anyhow::Context as _;
+use anyhow::*;
+use regex::Regex;
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{Cursor, Seek, SeekFrom, Write};
+use std::path::Path;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+// chapters longer than this are split into several files, readers get slow on huge ones
+const MAX_CHAPTER_BLOCKS: usize = 500;
+
+const CONTAINER: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>
+"#;
+
+struct Chapter {
+    title: String,
+    // first block
+    start: usize,
+}
+
+impl Chapter {
+    fn file_name(index: usize) -> String {
+        format!("chapter-{}.xhtml", index + 1)
+    }
+}
+
+/// Where the book goes, zip goes back to headers of its entries so it has to be seekable.
+pub enum EpubOut {
+    File(File),
+    /// kept in memory and written out when finished, for standard output
+    Memory(Cursor<Vec<u8>>, Box<dyn Write>),
+}
+
+impl EpubOut {
+    fn finish(self) -> std::io::Result<()> {
+        match self {
+            EpubOut::File(mut file) => file.flush(),
+            EpubOut::Memory(buffer, mut out) => {
+                out.write_all(&buffer.into_inner())?;
+                out.flush()
+            }
+        }
+    }
+}
+
+impl Write for EpubOut {
+    fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+        match self {
+            EpubOut::File(file) => file.write(bytes),
+            EpubOut::Memory(buffer, _) => buffer.write(bytes),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            EpubOut::File(file) => file.flush(),
+            EpubOut::Memory(..) => std::result::Result::Ok(()),
+        }
+    }
+}
+
+impl Seek for EpubOut {
+    fn seek(&mut self, position: SeekFrom) -> std::io::Result<u64> {
+        match self {
+            EpubOut::File(file) => file.seek(position),
+            EpubOut::Memory(buffer, _) => buffer.seek(position),
+        }
+    }
+}
+
+/// EPUB3 book with two columns layout, a file per chapter.
+///
+/// Chapters start at `headings` from `chapter_headings`, long ones are split.
+/// Blocks go into the zip as they come, only one chapter file is open at a time.
+pub struct EpubWriter<'a> {
+    zip: Option<ZipWriter<EpubOut>>,
+    inputs: &'a Inputs,
+    args: &'a OutputOptions,
+    kinds: Vec<ColumnKind>,
+    left_title: String,
+    right_title: String,
+    chapters: Vec<Chapter>,
+    // next chapter to open
+    next: usize,
+}
+
+impl<'a> EpubWriter<'a> {
+    pub fn new(
+        out: EpubOut,
+        inputs: &'a Inputs,
+        args: &'a OutputOptions,
+        headings: &[(usize, String)],
+        blocks: usize,
+    ) -> Self {
+        let (left_title, right_title) = book_titles(inputs, args);
+        let chapters = split_chapters(blocks, headings, &left_title);
+        Self {
+            zip: Some(ZipWriter::new(out)),
+            inputs,
+            args,
+            kinds: Layout::Columns(vec![ColumnKind::Left, ColumnKind::Right]).kinds(args),
+            left_title,
+            right_title,
+            chapters,
+            next: 0,
+        }
+    }
+
+    fn zip(&mut self) -> Result<&mut ZipWriter<EpubOut>> {
+        self.zip.as_mut().context("epub is already finished")
+    }
+
+    // starts the next chapter file, closing the one being written
+    fn open_chapter(&mut self) -> Result<()> {
+        let head = xhtml_head(self.args.main_lang(), &self.chapters[self.next].title);
+        let name = format!("OEBPS/{}", Chapter::file_name(self.next));
+        let close = self.next > 0;
+        self.next += 1;
+
+        let zip = self.zip()?;
+        if close {
+            zip.write_all(XHTML_TAIL.as_bytes())?;
+        }
+        zip.start_file(name, deflated())?;
+        zip.write_all(head.as_bytes())?;
+        Ok(())
+    }
+
+    fn add(&mut self, name: &str, contents: &str) -> Result<()> {
+        let zip = self.zip()?;
+        zip.start_file(name, deflated())?;
+        zip.write_all(contents.as_bytes())?;
+        Ok(())
+    }
+}
+
+fn deflated() -> SimpleFileOptions {
+    SimpleFileOptions::default().compression_method(CompressionMethod::Deflated)
+}
+
+impl BlockWriter for EpubWriter<'_> {
+    fn begin(&mut self) -> Result<()> {
+        let args = self.args;
+        // columns keep their order in right to left books, cells have their own direction
+        let mut css = format!(
+            r#"table {{ width: 100%; border-collapse: collapse; direction: ltr; }}
+td {{ vertical-align: top; padding: 0.2em; }}
+td.c1 {{ width: {:.1}%; }}
+td.c2 {{ width: {:.1}%; }}
+{}
+"#,
+            column_percents(args, 2)[0],
+            column_percents(args, 2)[1],
+            separator_css(args.separator),
+        );
+        if let Some(css_file) = &args.css {
+            css.push_str(
+                &std::fs::read_to_string(css_file)
+                    .with_context(|| format!("can not read css {}", css_file))?,
+            );
+        }
+
+        // mimetype has to be the first entry and uncompressed, readers sniff it at a fixed offset
+        let zip = self.zip()?;
+        zip.start_file(
+            "mimetype",
+            SimpleFileOptions::default().compression_method(CompressionMethod::Stored),
+        )?;
+        zip.write_all(b"application/epub+zip")?;
+
+        let package = package(
+            self.inputs,
+            args,
+            &self.left_title,
+            &self.right_title,
+            self.chapters.len(),
+        )?;
+        let nav = nav(args, &self.left_title, &self.right_title, &self.chapters);
+        self.add("META-INF/container.xml", CONTAINER)?;
+        self.add("OEBPS/content.opf", &package)?;
+        self.add("OEBPS/nav.xhtml", &nav)?;
+        self.add("OEBPS/style.css", &css)?;
+        Ok(())
+    }
+
+    fn block(&mut self, block: &Block) -> Result<()> {
+        let starts = self.chapters.get(self.next).map(|chapter| chapter.start);
+        if starts == Some(block.index) {
+            self.open_chapter()?;
+        }
+        let html = columns_block_html(block, &self.kinds, self.args);
+        self.zip()?.write_all(html.as_bytes())?;
+        Ok(())
+    }
+
+    fn end(&mut self) -> Result<()> {
+        // chapter of an empty text gets no blocks
+        while self.next < self.chapters.len() {
+            self.open_chapter()?;
+        }
+        let mut zip = self.zip.take().context("epub is already finished")?;
+        if self.next > 0 {
+            zip.write_all(XHTML_TAIL.as_bytes())?;
+        }
+        zip.finish()?.finish()?;
+        Ok(())
+    }
+}
+
+/// Titles of left and right texts: given ones or input file names.
+pub fn book_titles(inputs: &Inputs, args: &OutputOptions) -> (String, String) {
+    let left = args
+        .left_title
+        .clone()
+        .unwrap_or_else(|| file_stem(&inputs.left));
+    let right = args
+        .right_title
+        .clone()
+        .unwrap_or_else(|| file_stem(&inputs.right));
+    (left, right)
+}
+
+/// Blocks starting chapters with their titles: heading sentences of both sides.
+///
+/// Chapter starts at a block whose first left or right sentence matches `--chapter-pattern`.
+pub fn chapter_headings(
+    sequence: &[Vec<LRPair>],
+    left_sentences: &[String],
+    right_sentences: &[String],
+    args: &OutputOptions,
+) -> Result<Vec<(usize, String)>> {
+    let pattern = Regex::new(&format!("(?i){}", args.chapter_pattern))
+        .context("invalid --chapter-pattern")?;
+
+    let heading = |block: &[LRPair]| {
+        let (ls, rs) = block_indices(block);
+        let l = ls.first().and_then(|&i| left_sentences.get(i));
+        let r = rs.first().and_then(|&i| right_sentences.get(i));
+
+        let sides: Vec<&str> = [l, r].into_iter().flatten().map(|s| s.trim()).collect();
+        if sides.iter().any(|s| pattern.is_match(s)) {
+            Some(sides.join(" / "))
+        } else {
+            None
+        }
+    };
+
+    Ok(sequence
+        .iter()
+        .enumerate()
+        .filter_map(|(index, block)| heading(block).map(|title| (index, title)))
+        .collect())
+}
+
+// Chapters of `blocks` blocks, split where they are too long.
+fn split_chapters(blocks: usize, headings: &[(usize, String)], first_title: &str) -> Vec<Chapter> {
+    let mut starts = headings.to_vec();
+    // text before the first heading
+    if starts.first().is_none_or(|start| start.0 != 0) {
+        starts.insert(0, (0, first_title.to_string()));
+    }
+
+    let mut chapters = vec![];
+    for (i, (start, title)) in starts.iter().enumerate() {
+        let end = starts.get(i + 1).map_or(blocks, |next| next.0);
+        // at least one chapter, even of no blocks
+        for (part, start) in (*start..end.max(start + 1))
+            .step_by(MAX_CHAPTER_BLOCKS)
+            .enumerate()
+        {
+            let title = if part == 0 {
+                title.clone()
+            } else {
+                format!("{} ({})", title, part + 1)
+            };
+            chapters.push(Chapter { title, start });
+        }
+    }
+    chapters
+}
+
+fn package(
+    inputs: &Inputs,
+    args: &OutputOptions,
+    left_title: &str,
+    right_title: &str,
+    chapters: usize,
+) -> Result<String> {
+    let mut languages = format!(
+        "    <dc:language>{}</dc:language>\n",
+        escape_html(args.main_lang())
+    );
+    if args.second_lang() != args.main_lang() {
+        languages.push_str(&format!(
+            "    <dc:language>{}</dc:language>\n",
+            escape_html(args.second_lang())
+        ));
+    }
+    // pages are turned the way the book language is read
+    let progression = match direction(args.main_lang()) {
+        "auto" => String::new(),
+        dir => format!(" page-progression-direction=\"{}\"", dir),
+    };
+
+    let items: String = (0..chapters)
+        .map(|index| {
+            format!(
+                "    <item id=\"c{}\" href=\"{}\" media-type=\"application/xhtml+xml\"/>\n",
+                index + 1,
+                Chapter::file_name(index)
+            )
+        })
+        .collect();
+    let itemrefs: String = (0..chapters)
+        .map(|index| format!("    <itemref idref=\"c{}\"/>\n", index + 1))
+        .collect();
+
+    Ok(format!(
+        r##"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="book-id" xml:lang="{lang}">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:identifier id="book-id">urn:uuid:{uuid}</dc:identifier>
+    <dc:title id="left-title">{left_title}</dc:title>
+    <meta refines="#left-title" property="title-type">main</meta>
+    <dc:title id="right-title">{right_title}</dc:title>
+    <meta refines="#right-title" property="title-type">subtitle</meta>
+{languages}    <meta property="dcterms:modified">{modified}</meta>
+  </metadata>
+  <manifest>
+    <item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>
+    <item id="style" href="style.css" media-type="text/css"/>
+{items}  </manifest>
+  <spine{progression}>
+{itemrefs}  </spine>
+</package>
+"##,
+        lang = escape_html(args.main_lang()),
+        progression = progression,
+        uuid = book_uuid(inputs)?,
+        left_title = escape_html(left_title),
+        right_title = escape_html(right_title),
+        languages = languages,
+        modified = utc_now(),
+        items = items,
+        itemrefs = itemrefs,
+    ))
+}
+
+fn nav(args: &OutputOptions, left_title: &str, right_title: &str, chapters: &[Chapter]) -> String {
+    let entries: String = chapters
+        .iter()
+        .enumerate()
+        .map(|(index, chapter)| {
+            format!(
+                "      <li><a href=\"{}\">{}</a></li>\n",
+                Chapter::file_name(index),
+                escape_html(&chapter.title)
+            )
+        })
+        .collect();
+
+    let content = format!(
+        r#"
+  <nav epub:type="toc" id="toc">
+    <h1>{} / {}</h1>
+    <ol>
+{}    </ol>
+  </nav>
+"#,
+        escape_html(left_title),
+        escape_html(right_title),
+        entries
+    );
+    xhtml(args.main_lang(), "Contents", &content)
+}
+
+fn xhtml(lang: &str, title: &str, content: &str) -> String {
+    format!("{}{}{}", xhtml_head(lang, title), content, XHTML_TAIL)
+}
+
+fn xhtml_head(lang: &str, title: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops" lang="{lang}" xml:lang="{lang}"{dir}>
+<head>
+  <meta charset="UTF-8" />
+  <title>{title}</title>
+  <link rel="stylesheet" type="text/css" href="style.css" />
+</head>
+<body>
+"#,
+        lang = escape_html(lang),
+        dir = match direction(lang) {
+            "auto" => String::new(),
+            dir => format!(" dir=\"{}\"", dir),
+        },
+        title = escape_html(title),
+    )
+}
+
+const XHTML_TAIL: &str = "
+</body>
+</html>
+";
+
+// Same texts make the same book id, so readers treat a re-generated book as an update.
+fn book_uuid(inputs: &Inputs) -> Result<String> {
+    let mut hasher = Sha256::new();
+    hasher.update(file_hash(&inputs.left)?);
+    hasher.update(file_hash(&inputs.right)?);
+    let mut bytes: [u8; 16] = hasher.finalize()[..16].try_into()?;
+    // name-based UUID (version 5, RFC 4122 variant)
+    bytes[6] = (bytes[6] & 0x0f) | 0x50;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    Ok(format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    ))
+}
+
+// CCYY-MM-DDThh:mm:ssZ as required for dcterms:modified
+fn utc_now() -> String {
+    let seconds = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let days = (seconds / 86400) as i64;
+    let time = seconds % 86400;
+
+    // civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+fn file_stem(file: &str) -> String {
+    Path::new(file)
+        .file_stem()
+        .map_or(file.to_string(), |stem| stem.to_string_lossy().to_string())
+}
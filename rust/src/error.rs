@@ -0,0 +1,105 @@
+#[cfg(feature = "model")]
+use crate::manifest::MODEL_ID;
+use thiserror::Error;
+
+/// What can go wrong in aligning, messages say what to do about it.
+///
+/// Aligning and reading or checking the state dir return it. Writers of outputs (`write_outputs`,
+/// `produce_report`, single format writers) return `anyhow::Result` instead:
+/// their failures are only shown, with the file that could not be written as context.
+#[derive(Error, Debug)]
+pub enum AlignError {
+    #[cfg(feature = "model")]
+    #[error("can not load sentence embedding model {MODEL_ID}: it is downloaded on first run, so it needs network access and writable RUSTBERT_CACHE dir (~/.cache/.rustbert by default), and libtorch has to be the version tch expects")]
+    ModelLoad(#[source] rust_bert::RustBertError),
+
+    #[cfg(feature = "model")]
+    #[error("sentence embedding model failed on {sentences} sentences, if GPU memory ran out try smaller --window-size")]
+    Model {
+        sentences: usize,
+        #[source]
+        source: rust_bert::RustBertError,
+    },
+
+    #[cfg(feature = "model")]
+    #[error("can not compute similarity of embeddings")]
+    Tensor(#[source] tch::TchError),
+
+    #[error("can not access {file}")]
+    Io {
+        file: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("state file {file} is corrupted ({reason}), remove it to compute it again or use --force-restart to align from scratch")]
+    StateCorrupted { file: String, reason: String },
+
+    #[error("state dir {dir} does not match current run: {}; use --force-restart to discard it and align from scratch (or use another state dir)", problems.join(", "))]
+    StateMismatch { dir: String, problems: Vec<String> },
+
+    #[error("no alignment in {0}, align the texts first")]
+    NotAligned(String),
+
+    #[error("{0} was aligned for other texts, align them again")]
+    OtherTexts(String),
+
+    #[error(
+        "{0} has no sentences, it has to be plain UTF-8 text with at least one non-empty line"
+    )]
+    EmptyInput(String),
+
+    #[error(
+        "sentence embedding model returned embeddings of different sizes ({expected} and {found})"
+    )]
+    EmbeddingSize { expected: usize, found: usize },
+
+    #[error("{side} range {from}-{to} ends before it starts")]
+    ReversedRange {
+        side: String,
+        from: usize,
+        to: usize,
+    },
+
+    #[error("ranges go past the end of texts, there are {left} left and {right} right sentences")]
+    OutOfRange { left: usize, right: usize },
+
+    #[error("no alignment path found in window starting at left sentence {left_start} and right sentence {right_start}, check that anchors are in order on both sides or try bigger --window-size")]
+    NoPath {
+        left_start: usize,
+        right_start: usize,
+    },
+
+    #[error("alignment does not move past left sentence {left_start} and right sentence {right_start}, try bigger --window-size or add an anchor there")]
+    Stalled {
+        left_start: usize,
+        right_start: usize,
+    },
+}
+
+pub type AlignResult<T> = std::result::Result<T, AlignError>;
+
+impl AlignError {
+    /// For `map_err` of IO on a file.
+    pub fn io(file: &str) -> impl FnOnce(std::io::Error) -> Self + '_ {
+        move |source| AlignError::Io {
+            file: file.to_string(),
+            source,
+        }
+    }
+
+    // errors of a window are found relative to its start
+    #[cfg(feature = "model")]
+    pub(crate) fn offset(self, left: usize, right: usize) -> Self {
+        match self {
+            AlignError::NoPath {
+                left_start,
+                right_start,
+            } => AlignError::NoPath {
+                left_start: left_start + left,
+                right_start: right_start + right,
+            },
+            error => error,
+        }
+    }
+}
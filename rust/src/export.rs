@@ -0,0 +1,178 @@
+use crate::html::plain_text;
+use crate::options::OutputOptions;
+use crate::stream::{Block, BlockWriter};
+use anyhow::*;
+use serde::Serialize;
+use std::io::Write;
+
+/// Aligned block as a row of tabular exports.
+///
+/// Ranges are inclusive sentence indices, texts are sentences joined with spaces.
+#[derive(Serialize, Debug)]
+pub struct ExportBlock {
+    pub block: usize,
+    pub left_from: usize,
+    pub left_to: usize,
+    pub right_from: usize,
+    pub right_to: usize,
+    pub left_text: String,
+    pub right_text: String,
+    pub similarity: Option<f32>,
+}
+
+const COLUMNS: &[&str] = &[
+    "block",
+    "left_from",
+    "left_to",
+    "right_from",
+    "right_to",
+    "left_text",
+    "right_text",
+    "similarity",
+];
+
+impl ExportBlock {
+    /// Block with its texts, none when it is below `--min-similarity`.
+    ///
+    /// When filtering, blocks that have no similarity (no saved matrix) are left out too.
+    pub fn from_block(block: &Block, args: &OutputOptions) -> Option<Self> {
+        if let Some(min) = args.min_similarity {
+            if block.similarity.is_none_or(|similarity| similarity < min) {
+                return None;
+            }
+        }
+
+        let text = |sentences: &mut dyn Iterator<Item = &String>| {
+            sentences
+                .map(|s| plain_text(s, args.inline_markup))
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+
+        Some(ExportBlock {
+            block: block.index,
+            left_from: *block.left.first()?,
+            left_to: *block.left.last()?,
+            right_from: *block.right.first()?,
+            right_to: *block.right.last()?,
+            left_text: text(&mut block.left_texts()),
+            right_text: text(&mut block.right_texts()),
+            similarity: block.similarity,
+        })
+    }
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.block.to_string(),
+            self.left_from.to_string(),
+            self.left_to.to_string(),
+            self.right_from.to_string(),
+            self.right_to.to_string(),
+            self.left_text.clone(),
+            self.right_text.clone(),
+            self.similarity
+                .map_or(String::new(), |s| format!("{:.3}", s)),
+        ]
+    }
+}
+
+/// Tabular export formats.
+pub enum Table {
+    /// tab separated values with a header, tabs and line breaks in texts become spaces
+    Tsv,
+    /// comma separated values with a header (RFC 4180 quoting)
+    Csv,
+    /// JSON object per line, similarity is null when unknown
+    Jsonl,
+}
+
+/// Table of blocks, written row by row.
+pub struct TableWriter<'a, W: Write> {
+    out: W,
+    table: Table,
+    args: &'a OutputOptions,
+}
+
+impl<'a, W: Write> TableWriter<'a, W> {
+    pub fn new(out: W, table: Table, args: &'a OutputOptions) -> Self {
+        Self { out, table, args }
+    }
+}
+
+impl<W: Write> BlockWriter for TableWriter<'_, W> {
+    fn begin(&mut self) -> Result<()> {
+        match self.table {
+            Table::Tsv => writeln!(self.out, "{}", COLUMNS.join("\t"))?,
+            Table::Csv => write!(self.out, "{}\r\n", COLUMNS.join(","))?,
+            Table::Jsonl => {}
+        }
+        Ok(())
+    }
+
+    fn block(&mut self, block: &Block) -> Result<()> {
+        let Some(block) = ExportBlock::from_block(block, self.args) else {
+            return Ok(());
+        };
+        match self.table {
+            Table::Tsv => {
+                let fields: Vec<String> = block
+                    .fields()
+                    .iter()
+                    .map(|field| field.replace(['\t', '\n', '\r'], " "))
+                    .collect();
+                writeln!(self.out, "{}", fields.join("\t"))?;
+            }
+            Table::Csv => {
+                let fields: Vec<String> = block
+                    .fields()
+                    .iter()
+                    .map(|field| {
+                        if field.contains([',', '"', '\n', '\r']) {
+                            format!("\"{}\"", field.replace('"', "\"\""))
+                        } else {
+                            field.clone()
+                        }
+                    })
+                    .collect();
+                write!(self.out, "{}\r\n", fields.join(","))?;
+            }
+            Table::Jsonl => {
+                serde_json::to_writer(&mut self.out, &block)?;
+                writeln!(self.out)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stream::tests::write_one_to_one;
+
+    fn table(table: Table, left: &[&str], right: &[&str]) -> Result<String> {
+        let options = OutputOptions::default();
+        let mut out = Vec::new();
+        write_one_to_one(left, right, TableWriter::new(&mut out, table, &options))?;
+        Ok(String::from_utf8(out)?)
+    }
+
+    #[test]
+    fn csv_quotes_commas_quotes_and_line_breaks() -> Result<()> {
+        let csv = table(Table::Csv, &["Yes, \"he\" said."], &["Two\nlines"])?;
+        assert!(csv.ends_with("\r\n0,0,0,0,0,\"Yes, \"\"he\"\" said.\",\"Two\nlines\",\r\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn tsv_replaces_tabs_and_line_breaks() -> Result<()> {
+        let tsv = table(Table::Tsv, &["a\tb"], &["c\nd"])?;
+        assert!(tsv.ends_with("\n0\t0\t0\t0\t0\ta b\tc d\t\n"));
+        Ok(())
+    }
+}
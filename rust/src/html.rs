@@ -1,132 +1,582 @@
-const THREE_COLUMNS: &str = r#"<!DOCTYPE html>
-<html>
+use crate::language::{direction, UNKNOWN_LANGUAGE};
+use crate::options::{ColumnKind, OutputOptions, Separator};
+use crate::stream::{Block, BlockWriter};
+use anyhow::*;
+use std::io::Write;
+
+// Skeleton of every html output, `{{css}}` is replaced with generated style
+// (plus --css file) and `{{content}}` with aligned blocks.
+const TEMPLATE: &str = r#"<!DOCTYPE html>
+<html lang="{{lang}}">
 <head>
   <meta charset="UTF-8">
   <style>
-    @page {
-      size: A4 landscape;
-      margin: 0mm; /* Removes all page margins */
-      padding: 0mm;
-    }
+{{css}}
+  </style>
+</head>
+<body>
+{{content}}
+</body>
+</html>
+"#;
 
-    body {
-      margin: 0; /* Removes default body margins */
-      font-family: Arial, sans-serif;
-      font-size: 18pt;
+/// Html skeleton and extra css, built-in or given by user.
+pub struct Template {
+    html: String,
+    extra_css: String,
+    lang: String,
+}
+
+impl Template {
+    pub fn load(args: &OutputOptions) -> Result<Self> {
+        let html = match &args.template {
+            Some(file) => std::fs::read_to_string(file)
+                .with_context(|| format!("can not read template {}", file))?,
+            None => TEMPLATE.to_string(),
+        };
+        if !html.contains("{{content}}") {
+            bail!("template has no {{{{content}}}} placeholder");
+        }
+
+        let extra_css = match &args.css {
+            Some(file) => std::fs::read_to_string(file)
+                .with_context(|| format!("can not read css {}", file))?,
+            None => String::new(),
+        };
+
+        Ok(Self {
+            html,
+            extra_css,
+            lang: escape_html(args.main_lang()),
+        })
     }
 
-    table {
-      width: 100%;
-      border-collapse: collapse;
+    // Html before and after content, user css goes last to override generated rules.
+    fn parts(&self, css: &str) -> (String, String) {
+        let css = format!("{}\n{}", css, self.extra_css);
+        let fill = |part: &str| {
+            part.replace("{{css}}", &css)
+                .replace("{{lang}}", &self.lang)
+        };
+        let (head, tail) = self
+            .html
+            .split_once("{{content}}")
+            .unwrap_or((&self.html, ""));
+        (fill(head), fill(tail))
     }
-    td {
-      /*width: 50%;*/
-      width: 33%;
-      vertical-align: top;
-      padding: 5px;
-      padding-right: 0px;
-      border: none;
+}
+
+/// Style of `<hr />` between blocks.
+pub fn separator_css(separator: Separator) -> &'static str {
+    match separator {
+        Separator::Line => "",
+        Separator::Space => "hr { border: none; height: 0.7em; }",
+        Separator::None => "hr { display: none; }",
     }
-    * {
+}
+
+// Style shared by all layouts.
+fn base_css(args: &OutputOptions, default_page_size: &str) -> String {
+    let separator = separator_css(args.separator);
+
+    format!(
+        r#"    @page {{
+      size: {page_size};
+      margin: 0mm; /* Removes all page margins */
+      padding: 0mm;
+    }}
+
+    body {{
+      margin: 0; /* Removes default body margins */
+      font-family: {font_family};
+      font-size: {font_size};
+    }}
+
+    * {{
       margin: 0;
       padding: 0;
       box-sizing: border-box;
+    }}
+
+    {separator}
+"#,
+        page_size = args.page_size.as_deref().unwrap_or(default_page_size),
+        font_family = args.font_family,
+        font_size = args.font_size,
+        separator = separator,
+    )
+}
+
+/// Widths of the first `columns` text and notes columns in percents.
+///
+/// Given widths are relative to each other, for two columns the notes one is left out.
+pub fn column_percents(args: &OutputOptions, columns: usize) -> Vec<f32> {
+    let widths: Vec<f32> = (0..columns)
+        .map(|i| *args.column_widths.get(i).unwrap_or(&1) as f32)
+        .collect();
+    let total: f32 = widths.iter().sum::<f32>().max(1.);
+    widths.iter().map(|w| w * 100. / total).collect()
+}
+
+/// How column kinds are put on a page.
+pub enum Layout {
+    /// side by side in a table row per block
+    Columns(Vec<ColumnKind>),
+    /// one under another
+    Stacked(Vec<ColumnKind>),
+    /// first one as main text, the rest under it in smaller type
+    Interlinear(Vec<ColumnKind>),
+}
+
+impl Layout {
+    /// Kinds in order they are shown, left and right trade places with `--swap-sides`.
+    pub fn kinds(&self, args: &OutputOptions) -> Vec<ColumnKind> {
+        let kinds = match self {
+            Layout::Columns(kinds) | Layout::Stacked(kinds) | Layout::Interlinear(kinds) => kinds,
+        };
+        kinds
+            .iter()
+            .map(|&kind| match kind {
+                ColumnKind::Left if args.swap_sides => ColumnKind::Right,
+                ColumnKind::Right if args.swap_sides => ColumnKind::Left,
+                kind => kind,
+            })
+            .collect()
     }
-  </style>
-</head>
-<body>
-"#;
+}
 
-const TWO_COLUMNS: &str = r#"<!DOCTYPE html>
-<html>
-<head>
-  <meta charset="UTF-8">
-  <style>
-    @page {
-      size: A4 landscape;
-      margin: 0mm; /* Removes all page margins */
-      padding: 0mm;
+// Class of column or paragraph with given content.
+fn kind_class(kind: ColumnKind) -> &'static str {
+    match kind {
+        ColumnKind::Left => "left",
+        ColumnKind::Right => "right",
+        ColumnKind::Notes => "notes",
+        ColumnKind::Index => "index",
+        ColumnKind::Similarity => "similarity",
     }
+}
 
-    body {
-      margin: 0; /* Removes default body margins */
-      font-family: Arial, sans-serif;
-      font-size: 18pt;
+// Text and notes columns share the width, the rest take as little as they need.
+fn is_wide(kind: ColumnKind) -> bool {
+    matches!(
+        kind,
+        ColumnKind::Left | ColumnKind::Right | ColumnKind::Notes
+    )
+}
+
+const MARGINAL_CSS: &str = r#"
+    .index, .similarity {
+      font-size: 60%;
+      color: gray;
     }
+"#;
 
-    table {
+fn columns_css(args: &OutputOptions, kinds: &[ColumnKind]) -> String {
+    let wide = kinds.iter().filter(|&&kind| is_wide(kind)).count();
+    let mut widths = column_percents(args, wide).into_iter();
+
+    let mut columns = String::new();
+    for (i, &kind) in kinds.iter().enumerate() {
+        if is_wide(kind) {
+            let width = widths.next().unwrap_or(0.);
+            columns.push_str(&format!("    td.c{} {{ width: {:.1}%; }}\n", i + 1, width));
+        } else {
+            columns.push_str(&format!(
+                "    td.c{} {{ width: 1%; white-space: nowrap; }}\n",
+                i + 1
+            ));
+        }
+    }
+
+    format!(
+        r#"{base}
+    table {{
       width: 100%;
       border-collapse: collapse;
-    }
-    td {
-      width: 50%;
+    }}
+    td {{
       vertical-align: top;
       padding: 5px;
       padding-right: 0px;
       border: none;
+    }}
+{columns}{marginal}"#,
+        base = base_css(args, "A4 landscape"),
+        columns = columns,
+        marginal = MARGINAL_CSS,
+    )
+}
+
+// Texts are told apart by their order: the first one is bold, the second one green.
+fn stacked_css(args: &OutputOptions, kinds: &[ColumnKind]) -> String {
+    let texts = kinds
+        .iter()
+        .filter(|&&kind| matches!(kind, ColumnKind::Left | ColumnKind::Right))
+        .map(|&kind| kind_class(kind));
+    let mut emphasis = String::new();
+    for (class, style) in texts.zip(["font-weight: bold;", "color: green;"]) {
+        emphasis.push_str(&format!("    p.{} {{ {} }}\n", class, style));
     }
-    * {
-      margin: 0;
-      padding: 0;
-      box-sizing: border-box;
+
+    format!(
+        r#"{base}
+    p.left {{
+      text-align: left;
+    }}
+
+    p.right {{
+      text-align: right;
+    }}
+{emphasis}{marginal}"#,
+        base = base_css(args, "A4"),
+        emphasis = emphasis,
+        marginal = MARGINAL_CSS,
+    )
+}
+
+fn interlinear_css(args: &OutputOptions) -> String {
+    format!(
+        r#"{base}
+    body {{
+      padding: 1em;
+    }}
+
+    div.b {{
+      margin: 0.5em 0;
+    }}
+
+    .gloss {{
+      font-size: 75%;
+      color: #555;
+    }}
+
+    .flow span.b {{
+      display: inline-flex;
+      flex-direction: column;
+      vertical-align: top;
+      margin: 0 0.4em 0.8em 0;
+    }}
+{marginal}"#,
+        base = base_css(args, "A4"),
+        marginal = MARGINAL_CSS,
+    )
+}
+
+/// Html page of a layout, written block by block.
+pub struct HtmlWriter<'a, W: Write> {
+    out: W,
+    layout: &'a Layout,
+    kinds: Vec<ColumnKind>,
+    args: &'a OutputOptions,
+    template: &'a Template,
+    tail: String,
+}
+
+impl<'a, W: Write> HtmlWriter<'a, W> {
+    pub fn new(
+        out: W,
+        layout: &'a Layout,
+        args: &'a OutputOptions,
+        template: &'a Template,
+    ) -> Self {
+        Self {
+            out,
+            layout,
+            kinds: layout.kinds(args),
+            args,
+            template,
+            tail: String::new(),
+        }
     }
-  </style>
-</head>
-<body>
-"#;
 
-const ONE_COLUMN: &str = r#"<!DOCTYPE html>
-<html>
-<head>
-  <meta charset="UTF-8">
-  <style>
-    @page {
-      size: A4;
-      margin: 0mm; /* Removes all page margins */
-      padding: 0mm;
+    fn flow(&self) -> bool {
+        matches!(self.layout, Layout::Interlinear(_)) && self.args.interlinear_flow
     }
+}
 
-    body {
-      margin: 0; /* Removes default body margins */
-      font-family: Arial, sans-serif;
-      font-size: 18pt;
+impl<W: Write> BlockWriter for HtmlWriter<'_, W> {
+    fn begin(&mut self) -> Result<()> {
+        let css = match self.layout {
+            Layout::Columns(_) => columns_css(self.args, &self.kinds),
+            Layout::Stacked(_) => stacked_css(self.args, &self.kinds),
+            Layout::Interlinear(_) => interlinear_css(self.args),
+        };
+        let (head, tail) = self.template.parts(&css);
+        self.out.write_all(head.as_bytes())?;
+        if self.flow() {
+            self.out.write_all(b"\n  <div class=\"flow\">")?;
+        }
+        self.tail = tail;
+        Ok(())
     }
 
-    * {
-      margin: 0;
-      padding: 0;
-      box-sizing: border-box;
+    fn block(&mut self, block: &Block) -> Result<()> {
+        let html = match self.layout {
+            Layout::Columns(_) => columns_block_html(block, &self.kinds, self.args),
+            Layout::Stacked(_) => stacked_block_html(block, &self.kinds, self.args),
+            Layout::Interlinear(_) => interlinear_block_html(block, &self.kinds, self.args),
+        };
+        self.out.write_all(html.as_bytes())?;
+        Ok(())
     }
 
-    .l {
-      text-align: start;
-      color: green;
+    fn end(&mut self) -> Result<()> {
+        if self.flow() {
+            self.out.write_all(b"\n  </div>\n")?;
+        }
+        self.out.write_all(self.tail.as_bytes())?;
+        self.out.flush()?;
+        Ok(())
     }
+}
 
-    .r {
-      text-align: end;
-      font-weight: bold;
+// Behavior of `reader.html`, `{{pairs}}` is the path as [left, right] pairs,
+// `{{key}}` is localStorage key for notes and settings of the book.
+const READER_SCRIPT: &str = r#"
+<script>
+(function () {
+  const PAIRS = {{pairs}};
+  const KEY = {{key}};
+
+  const counterparts = { l: new Map(), r: new Map() };
+  for (const [l, r] of PAIRS) {
+    if (!counterparts.l.has(l)) counterparts.l.set(l, []);
+    if (!counterparts.r.has(r)) counterparts.r.set(r, []);
+    counterparts.l.get(l).push(r);
+    counterparts.r.get(r).push(l);
+  }
+
+  // sentence and its counterparts
+  let highlighted = [];
+  function highlight(span) {
+    highlighted.forEach(x => x.classList.remove('hl'));
+    highlighted = [];
+    if (!span) return;
+    const side = span.dataset.l !== undefined ? 'l' : 'r';
+    const other = side === 'l' ? 'r' : 'l';
+    const others = counterparts[side].get(Number(span.dataset[side])) || [];
+    highlighted = [span].concat(
+      others.flatMap(i => Array.from(document.querySelectorAll('[data-' + other + '="' + i + '"]')))
+    );
+    highlighted.forEach(x => x.classList.add('hl'));
+  }
+  document.addEventListener('mouseover', e => highlight(e.target.closest('.s')));
+
+  // hidden column is revealed block by block on tap
+  const hide = document.getElementById('hide');
+  function setHidden(column) {
+    document.body.classList.remove('hide-c1', 'hide-c2');
+    document.querySelectorAll('.revealed').forEach(x => x.classList.remove('revealed'));
+    if (column) document.body.classList.add('hide-' + column);
+    localStorage.setItem(KEY + ':hide', column);
+  }
+  hide.value = localStorage.getItem(KEY + ':hide') || '';
+  setHidden(hide.value);
+  hide.addEventListener('change', () => setHidden(hide.value));
+
+  document.addEventListener('click', e => {
+    const cell = e.target.closest('td.c1, td.c2');
+    const column = cell && (cell.classList.contains('c1') ? 'c1' : 'c2');
+    if (column && document.body.classList.contains('hide-' + column) && !cell.classList.contains('revealed')) {
+      cell.classList.add('revealed');
+      return;
     }
-  </style>
-</head>
-<body>
+    highlight(e.target.closest('.s'));
+  });
+
+  // notes column
+  const notes = JSON.parse(localStorage.getItem(KEY + ':notes') || '{}');
+  document.querySelectorAll('td.c3').forEach(cell => {
+    cell.textContent = notes[cell.dataset.block] || '';
+    cell.addEventListener('input', () => {
+      const text = cell.innerText.trim();
+      if (text) notes[cell.dataset.block] = text;
+      else delete notes[cell.dataset.block];
+      localStorage.setItem(KEY + ':notes', JSON.stringify(notes));
+    });
+  });
+
+  document.getElementById('export').addEventListener('click', () => {
+    const rows = Object.keys(notes).map(Number).sort((a, b) => a - b).map(block => {
+      const row = document.getElementById('b' + block);
+      return {
+        block: block,
+        first: row ? row.querySelector('td.c1').innerText.trim() : '',
+        second: row ? row.querySelector('td.c2').innerText.trim() : '',
+        note: notes[block]
+      };
+    });
+    const link = document.createElement('a');
+    link.href = URL.createObjectURL(new Blob([JSON.stringify(rows, null, 2)], { type: 'application/json' }));
+    link.download = 'notes.json';
+    link.click();
+  });
+})();
+</script>
 "#;
 
-pub fn produce_html(
-    left_sentences: &Vec<String>,
-    right_sentences: &Vec<String>,
-    path: &Vec<(usize, usize)>,
-    columns: usize,
-) -> String {
-    let alignment = get_sequence(path);
-    if columns == 3 {
-        build_html_from_sequence(&left_sentences, &right_sentences, &alignment, THREE_COLUMNS)
-    } else if columns == 2 {
-        build_html_from_sequence(&left_sentences, &right_sentences, &alignment, TWO_COLUMNS)
-    } else if columns == 1 {
-        build_html_one_column(&left_sentences, &right_sentences, &alignment)
-    } else {
-        todo!();
+fn reader_css(args: &OutputOptions) -> String {
+    format!(
+        r#"{columns}
+    #toolbar {{
+      position: sticky;
+      top: 0;
+      z-index: 1;
+      background: #fff;
+      border-bottom: 1px solid #ccc;
+      padding: 4px 5px;
+      font-size: 12pt;
+    }}
+    #toolbar select, #toolbar button {{ font-size: 12pt; margin-right: 1em; }}
+    .s {{ cursor: pointer; }}
+    .s.hl {{ background: #ffe58a; }}
+    body.hide-c1 td.c1:not(.revealed) .s,
+    body.hide-c2 td.c2:not(.revealed) .s {{ filter: blur(6px); }}
+    td.c3 {{ border-left: 1px dashed #ccc; outline: none; white-space: pre-wrap; }}
+    td.c3:focus {{ background: #f6f6f0; }}
+    @media print {{ #toolbar {{ display: none; }} }}
+"#,
+        columns = columns_css(
+            args,
+            &[ColumnKind::Left, ColumnKind::Right, ColumnKind::Notes]
+        ),
+    )
+}
+
+/// Interactive three columns page for studying, works offline as a single file.
+///
+/// Hovering or tapping a sentence highlights its counterparts, one column can be hidden
+/// and revealed block by block, notes column is editable and kept in browser's localStorage.
+pub struct ReaderWriter<'a, W: Write> {
+    out: W,
+    path: &'a [(usize, usize)],
+    args: &'a OutputOptions,
+    template: &'a Template,
+    left_title: &'a str,
+    right_title: &'a str,
+    kinds: Vec<ColumnKind>,
+    tail: String,
+}
+
+impl<'a, W: Write> ReaderWriter<'a, W> {
+    pub fn new(
+        out: W,
+        path: &'a [(usize, usize)],
+        args: &'a OutputOptions,
+        template: &'a Template,
+        left_title: &'a str,
+        right_title: &'a str,
+    ) -> Self {
+        Self {
+            out,
+            path,
+            args,
+            template,
+            left_title,
+            right_title,
+            kinds: Layout::Columns(vec![ColumnKind::Left, ColumnKind::Right]).kinds(args),
+            tail: String::new(),
+        }
+    }
+}
+
+impl<W: Write> BlockWriter for ReaderWriter<'_, W> {
+    fn begin(&mut self) -> Result<()> {
+        let (first_title, second_title) = if self.args.swap_sides {
+            (self.right_title, self.left_title)
+        } else {
+            (self.left_title, self.right_title)
+        };
+
+        let (head, tail) = self.template.parts(&reader_css(self.args));
+        write!(
+            self.out,
+            r#"{head}
+  <div id="toolbar">
+    <label>hide <select id="hide">
+      <option value="">nothing</option>
+      <option value="c1">{first}</option>
+      <option value="c2">{second}</option>
+    </select></label>
+    <button id="export">export notes</button>
+  </div>
+"#,
+            head = head,
+            first = escape_html(first_title),
+            second = escape_html(second_title)
+        )?;
+        self.tail = tail;
+        Ok(())
+    }
+
+    fn block(&mut self, block: &Block) -> Result<()> {
+        let args = self.args;
+        let spans = |indices: &[usize], sentences: &[String], side: &str, lang: &str| {
+            indices
+                .iter()
+                .filter_map(|&i| sentences.get(i).map(|s| (i, s)))
+                .map(|(i, s)| {
+                    format!(
+                        "<span class=\"s\" data-{}=\"{}\" dir=\"{}\">{}</span>",
+                        side,
+                        i,
+                        direction(lang),
+                        sentence_html(s, args.inline_markup)
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("<br />")
+        };
+        let left = spans(&block.left, block.left_sentences, "l", &args.left_lang);
+        let right = spans(&block.right, block.right_sentences, "r", &args.right_lang);
+        let (first, second) = if args.swap_sides {
+            (right, left)
+        } else {
+            (left, right)
+        };
+
+        write!(
+            self.out,
+            r#"
+  <hr />
+  <table id="b{index}">
+    <tr>
+      <td class="c1"{first_language}>
+        <p>{first}</p>
+      </td>
+      <td class="c2"{second_language}>
+        <p>{second}</p>
+      </td>
+      <td class="c3" contenteditable="true" dir="auto" data-block="{index}"></td>
+    </tr>
+  </table>
+"#,
+            index = block.index,
+            first = first,
+            second = second,
+            first_language = language_attributes(self.kinds[0], args),
+            second_language = language_attributes(self.kinds[1], args),
+        )?;
+        Ok(())
+    }
+
+    fn end(&mut self) -> Result<()> {
+        // json inside of script element must not close it
+        let json = |value: String| value.replace("</", "<\\/");
+        let pairs = serde_json::to_string(self.path).unwrap_or_else(|_| "[]".to_string());
+        let key = serde_json::to_string(&format!(
+            "make-parallel-text:{}:{}",
+            self.left_title, self.right_title
+        ))
+        .unwrap_or_else(|_| "\"make-parallel-text\"".to_string());
+        let script = READER_SCRIPT
+            .replace("{{pairs}}", &json(pairs))
+            .replace("{{key}}", &json(key));
+
+        self.out.write_all(script.as_bytes())?;
+        self.out.write_all(self.tail.as_bytes())?;
+        self.out.flush()?;
+        Ok(())
     }
 }
 
@@ -180,131 +630,326 @@ pub fn get_sequence(path: &[(usize, usize)]) -> Vec<Vec<LRPair>> {
     pairs
 }
 
-/// Builds the final HTML
+/// Sorted unique left and right sentence indices of a block from `get_sequence`.
+pub fn block_indices(block: &[LRPair]) -> (Vec<usize>, Vec<usize>) {
+    let mut ls: Vec<usize> = block.iter().map(|lr| lr.l).collect();
+    ls.sort_unstable();
+    ls.dedup();
+
+    let mut rs: Vec<usize> = block.iter().map(|lr| lr.r).collect();
+    rs.sort_unstable();
+    rs.dedup();
+
+    (ls, rs)
+}
+
+/// Escapes text for HTML content and attribute values.
 ///
-/// - `left_sentences`: text that would normally go on the left.
-/// - `right_sentences`: text that would normally go on the right.
-/// - `sequence`: the output from `get_sequence`.
+/// ```
+/// use make_parallel_text::*;
 ///
-/// This replicates your original Ruby structure
-pub fn build_html_from_sequence(
-    left_sentences: &[String],
-    right_sentences: &[String],
-    sequence: &[Vec<LRPair>],
-    html_header: &str,
-) -> String {
-    let mut html_content = html_header.to_owned();
-
-    // For each "block" in the sequence
-    for xs in sequence {
-        // Collect all L values
-        let mut ls: Vec<usize> = xs.iter().map(|lr| lr.l).collect();
-        ls.sort_unstable();
-        ls.dedup();
-
-        // Collect all R values
-        let mut rs: Vec<usize> = xs.iter().map(|lr| lr.r).collect();
-        rs.sort_unstable();
-        rs.dedup();
-
-        // Build the final strings for each side
-        let left: Vec<&str> = ls
-            .iter()
-            .filter_map(|&index| left_sentences.get(index))
-            .map(|s| s.trim())
-            .collect();
-        let right: Vec<&str> = rs
+/// assert_eq!(escape_html(r#"a < b & "c""#), "a &lt; b &amp; &quot;c&quot;");
+/// ```
+pub fn escape_html(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\'' => result.push_str("&#39;"),
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+// inline formatting that `inline_markup_html` lets through, only bare tags without attributes
+const INLINE_TAGS: &[&str] = &["i", "b", "em", "strong"];
+
+/// Escapes text except for whitelisted inline formatting tags (italics, bold).
+///
+/// Sentence splitting cuts formatting apart, so tags left open are closed at the end,
+/// closing tags without opening ones are dropped.
+///
+/// ```
+/// use make_parallel_text::*;
+///
+/// assert_eq!(
+///     inline_markup_html("<i>sing, o goddess</i> <script>x</script>"),
+///     "<i>sing, o goddess</i> &lt;script&gt;x&lt;/script&gt;"
+/// );
+/// assert_eq!(inline_markup_html("<b>half. "), "<b>half. </b>");
+/// assert_eq!(inline_markup_html("other half</b>."), "other half.");
+/// assert_eq!(
+///     inline_markup_html("<i onclick=x>a</i>"),
+///     "&lt;i onclick=x&gt;a"
+/// );
+/// ```
+pub fn inline_markup_html(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut open: Vec<&str> = vec![];
+    let mut rest = text;
+
+    while let Some(c) = rest.chars().next() {
+        let tag = INLINE_TAGS.iter().find_map(|&tag| {
+            [(false, format!("<{}>", tag)), (true, format!("</{}>", tag))]
+                .into_iter()
+                .find(|(_, markup)| {
+                    rest.get(..markup.len())
+                        .is_some_and(|x| x.eq_ignore_ascii_case(markup))
+                })
+                .map(|(closing, markup)| (tag, closing, markup.len()))
+        });
+
+        match tag {
+            Some((tag, false, len)) => {
+                open.push(tag);
+                result.push_str(&format!("<{}>", tag));
+                rest = &rest[len..];
+            }
+            Some((tag, true, len)) => {
+                // closes everything opened after it too
+                if let Some(at) = open.iter().rposition(|&x| x == tag) {
+                    for tag in open.drain(at..).rev() {
+                        result.push_str(&format!("</{}>", tag));
+                    }
+                }
+                rest = &rest[len..];
+            }
+            None => {
+                result.push_str(&escape_html(&rest[..c.len_utf8()]));
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+
+    for tag in open.into_iter().rev() {
+        result.push_str(&format!("</{}>", tag));
+    }
+    result
+}
+
+/// Sentence as plain text for outputs other than html.
+///
+/// With `--inline-markup` formatting tags that html would keep are dropped, otherwise text is as is.
+///
+/// ```
+/// use make_parallel_text::*;
+///
+/// assert_eq!(plain_text(" <i>sing</i>, o <x>goddess ", true), "sing, o <x>goddess");
+/// assert_eq!(plain_text("<i>sing</i>", false), "<i>sing</i>");
+/// ```
+pub fn plain_text(sentence: &str, inline_markup: bool) -> String {
+    if !inline_markup {
+        return sentence.trim().to_string();
+    }
+
+    let mut result = String::with_capacity(sentence.len());
+    let mut rest = sentence.trim();
+    while let Some(c) = rest.chars().next() {
+        let tag = INLINE_TAGS
             .iter()
-            .filter_map(|&index| right_sentences.get(index))
-            .map(|s| s.trim())
-            .collect();
+            .flat_map(|tag| [format!("<{}>", tag), format!("</{}>", tag)])
+            .find(|markup| {
+                rest.get(..markup.len())
+                    .is_some_and(|x| x.eq_ignore_ascii_case(markup))
+            });
+
+        match tag {
+            Some(markup) => rest = &rest[markup.len()..],
+            None => {
+                result.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+    result
+}
 
-        let right_joined = right.join("<br />");
-        let left_joined = left.join("<br />");
+// Sentence text as HTML content.
+fn sentence_html(sentence: &str, inline_markup: bool) -> String {
+    if inline_markup {
+        inline_markup_html(sentence.trim())
+    } else {
+        escape_html(sentence.trim())
+    }
+}
 
-        html_content.push_str(&format!(
-            r#"
+// Block of columns layout: a table row with a cell per kind.
+pub fn columns_block_html(block: &Block, kinds: &[ColumnKind], args: &OutputOptions) -> String {
+    let texts = block_html(block, args);
+
+    let mut cells = String::new();
+    for (column, &kind) in kinds.iter().enumerate() {
+        let class = format!("c{} {}", column + 1, kind_class(kind));
+        let language = language_attributes(kind, args);
+        match cell_html(kind, block, &texts) {
+            Some(cell) => cells.push_str(&format!(
+                r#"
+      <td class="{class}"{language}>
+        <p>{cell}</p>
+      </td>"#
+            )),
+            None => cells.push_str(&format!("\n      <td class=\"{}\"></td>", class)),
+        }
+    }
+
+    format!(
+        r#"
   <hr />
-  <table>
-    <tr>
-      <td>
-        <p>{left_part}</p>
-      </td>
-      <td>
-        <p>{right_part}</p>
-      </td>
-      <td></td>
+  <table id="b{index}">
+    <tr>{cells}
     </tr>
   </table>
 "#,
-            right_part = right_joined,
-            left_part = left_joined
-        ));
-    }
+        index = block.index,
+        cells = cells,
+    )
+}
 
-    // Close the HTML body and tags
-    html_content.push_str(
+// Block of one column layout, a paragraph per kind.
+fn stacked_block_html(block: &Block, kinds: &[ColumnKind], args: &OutputOptions) -> String {
+    let texts = block_html(block, args);
+
+    let paragraphs: Vec<String> = kinds
+        .iter()
+        .map(|&kind| {
+            format!(
+                "<p class=\"{}\"{}>{}</p>",
+                kind_class(kind),
+                language_attributes(kind, args),
+                cell_html(kind, block, &texts).unwrap_or_default()
+            )
+        })
+        .collect();
+
+    format!(
         r#"
-</body>
-</html>
+    <hr />
+    {block}
 "#,
-    );
-
-    html_content
+        block = paragraphs.join("\n    ")
+    )
 }
 
-pub fn build_html_one_column(
-    left_sentences: &[String],
-    right_sentences: &[String],
-    sequence: &[Vec<LRPair>],
-) -> String {
-    let mut html_content = ONE_COLUMN.to_owned();
-
-    // For each "block" in the sequence
-    for xs in sequence {
-        // Collect all L values
-        let mut ls: Vec<usize> = xs.iter().map(|lr| lr.l).collect();
-        ls.sort_unstable();
-        ls.dedup();
-
-        // Collect all R values
-        let mut rs: Vec<usize> = xs.iter().map(|lr| lr.r).collect();
-        rs.sort_unstable();
-        rs.dedup();
-
-        // Build the final strings for each side
-        let left: Vec<&str> = ls
-            .iter()
-            .filter_map(|&index| left_sentences.get(index))
-            .map(|s| s.trim())
-            .collect();
-        let right: Vec<&str> = rs
-            .iter()
-            .filter_map(|&index| right_sentences.get(index))
-            .map(|s| s.trim())
-            .collect();
-
-        let right_joined = right.join("<br />");
-        let left_joined = left.join("<br />");
-
-        html_content.push_str(&format!(
+// Block of interlinear layout: first kind is main text, the rest go under it as glosses.
+//
+// With `--interlinear-flow` blocks follow each other in a line like words of a paragraph,
+// sentences of a block are not broken into lines then.
+fn interlinear_block_html(block: &Block, kinds: &[ColumnKind], args: &OutputOptions) -> String {
+    let (element, line_break) = if args.interlinear_flow {
+        ("span", " ")
+    } else {
+        ("p", "<br />")
+    };
+    let texts = block_html(block, args);
+
+    let lines: Vec<String> = kinds
+        .iter()
+        .enumerate()
+        .map(|(line, &kind)| {
+            let class = if line == 0 {
+                kind_class(kind).to_string()
+            } else {
+                format!("{} gloss", kind_class(kind))
+            };
+            let text = cell_html(kind, block, &texts).unwrap_or_default();
+            format!(
+                "<{element} class=\"{class}\"{language}>{text}</{element}>",
+                element = element,
+                class = class,
+                language = language_attributes(kind, args),
+                text = text.replace("<br />", line_break),
+            )
+        })
+        .collect();
+
+    if args.interlinear_flow {
+        format!(
+            "\n    <span class=\"b\" id=\"b{}\">{}</span>",
+            block.index,
+            lines.join("")
+        )
+    } else {
+        format!(
             r#"
     <hr />
-    <p class=r>{right_part}</p>
-    <p class=l>{left_part}</p>
+    <div class="b" id="b{index}">
+      {lines}
+    </div>
 "#,
-            right_part = right_joined,
-            left_part = left_joined
-        ));
+            index = block.index,
+            lines = lines.join("\n      ")
+        )
     }
+}
 
-    // Close the HTML body and tags
-    html_content.push_str(
-        r#"
-</body>
-</html>
-"#,
-    );
+// Content of a column of a block, notes have none.
+fn cell_html(kind: ColumnKind, block: &Block, (left, right): &(String, String)) -> Option<String> {
+    match kind {
+        ColumnKind::Left => Some(left.clone()),
+        ColumnKind::Right => Some(right.clone()),
+        ColumnKind::Notes => None,
+        ColumnKind::Index => Some(format!(
+            "{}<br />{}<br />{}",
+            block.index,
+            range_text(&block.left),
+            range_text(&block.right)
+        )),
+        ColumnKind::Similarity => Some(
+            block
+                .similarity
+                .map_or(String::new(), |similarity| format!("{:.2}", similarity)),
+        ),
+    }
+}
+
+// Inclusive range of sentence indices like "4-6", a single index when it is one sentence.
+pub fn range_text(indices: &[usize]) -> String {
+    match (indices.first(), indices.last()) {
+        (Some(first), Some(last)) if first != last => format!("{}-{}", first, last),
+        (Some(first), _) => first.to_string(),
+        _ => String::new(),
+    }
+}
+
+// Escaped texts of both sides of a block, sentences on own lines.
+//
+// Every sentence is isolated in direction of its side, so that its neutral
+// characters (punctuation, numbers) do not get mixed with neighbouring ones.
+fn block_html(block: &Block, args: &OutputOptions) -> (String, String) {
+    let side = |sentences: &mut dyn Iterator<Item = &String>, lang: &str| {
+        sentences
+            .map(|s| {
+                format!(
+                    "<bdi dir=\"{}\">{}</bdi>",
+                    direction(lang),
+                    sentence_html(s, args.inline_markup)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("<br />")
+    };
+
+    (
+        side(&mut block.left_texts(), &args.left_lang),
+        side(&mut block.right_texts(), &args.right_lang),
+    )
+}
 
-    html_content
+// `lang` and `dir` of a text column, nothing for the rest.
+fn language_attributes(kind: ColumnKind, args: &OutputOptions) -> String {
+    let lang = match kind {
+        ColumnKind::Left => &args.left_lang,
+        ColumnKind::Right => &args.right_lang,
+        _ => return String::new(),
+    };
+    let dir = direction(lang);
+    if lang == UNKNOWN_LANGUAGE {
+        format!(" dir=\"{}\"", dir)
+    } else {
+        format!(" lang=\"{}\" dir=\"{}\"", escape_html(lang), dir)
+    }
 }
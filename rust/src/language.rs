@@ -0,0 +1,158 @@
+use crate::options::OutputOptions;
+
+/// Language code for unknown language, it is what `--left-lang`/`--right-lang` default to.
+pub const UNKNOWN_LANGUAGE: &str = "und";
+
+// how much of a text is enough to recognize its language
+const SAMPLE_SENTENCES: usize = 300;
+
+// ISO 639-3 codes of whatlang to the shorter ISO 639-1 ones where they exist, as BCP 47 requires
+const SHORT_CODES: &[(&str, &str)] = &[
+    ("afr", "af"),
+    ("aka", "ak"),
+    ("amh", "am"),
+    ("ara", "ar"),
+    ("aze", "az"),
+    ("bel", "be"),
+    ("ben", "bn"),
+    ("bul", "bg"),
+    ("cat", "ca"),
+    ("ces", "cs"),
+    ("cmn", "zh"),
+    ("dan", "da"),
+    ("deu", "de"),
+    ("ell", "el"),
+    ("eng", "en"),
+    ("epo", "eo"),
+    ("est", "et"),
+    ("fin", "fi"),
+    ("fra", "fr"),
+    ("guj", "gu"),
+    ("heb", "he"),
+    ("hin", "hi"),
+    ("hrv", "hr"),
+    ("hun", "hu"),
+    ("hye", "hy"),
+    ("ind", "id"),
+    ("ita", "it"),
+    ("jav", "jv"),
+    ("jpn", "ja"),
+    ("kan", "kn"),
+    ("kat", "ka"),
+    ("khm", "km"),
+    ("kor", "ko"),
+    ("lat", "la"),
+    ("lav", "lv"),
+    ("lit", "lt"),
+    ("mal", "ml"),
+    ("mar", "mr"),
+    ("mkd", "mk"),
+    ("mya", "my"),
+    ("nep", "ne"),
+    ("nld", "nl"),
+    ("nob", "nb"),
+    ("ori", "or"),
+    ("pan", "pa"),
+    ("pes", "fa"),
+    ("pol", "pl"),
+    ("por", "pt"),
+    ("ron", "ro"),
+    ("rus", "ru"),
+    ("sin", "si"),
+    ("slk", "sk"),
+    ("slv", "sl"),
+    ("sna", "sn"),
+    ("spa", "es"),
+    ("srp", "sr"),
+    ("swe", "sv"),
+    ("tam", "ta"),
+    ("tel", "te"),
+    ("tgl", "tl"),
+    ("tha", "th"),
+    ("tuk", "tk"),
+    ("tur", "tr"),
+    ("ukr", "uk"),
+    ("urd", "ur"),
+    ("uzb", "uz"),
+    ("vie", "vi"),
+    ("yid", "yi"),
+    ("zul", "zu"),
+];
+
+/// BCP 47 code of the language sentences are written in, if it can be recognized reliably.
+pub fn detect_language(sentences: &[String]) -> Option<String> {
+    let sample = sentences
+        .iter()
+        .take(SAMPLE_SENTENCES)
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let info = whatlang::detect(&sample).filter(|info| info.is_reliable())?;
+    let code = info.lang().code();
+    let short = SHORT_CODES
+        .iter()
+        .find(|(long, _)| *long == code)
+        .map_or(code, |(_, short)| short);
+    Some(short.to_string())
+}
+
+/// Output options with unknown languages replaced by detected ones.
+pub fn with_languages(
+    options: &OutputOptions,
+    left_sentences: &[String],
+    right_sentences: &[String],
+) -> OutputOptions {
+    let resolve = |lang: &str, sentences: &[String]| {
+        if lang != UNKNOWN_LANGUAGE {
+            return lang.to_string();
+        }
+        detect_language(sentences).unwrap_or_else(|| lang.to_string())
+    };
+
+    OutputOptions {
+        left_lang: resolve(&options.left_lang, left_sentences),
+        right_lang: resolve(&options.right_lang, right_sentences),
+        ..options.clone()
+    }
+}
+
+// primary subtags of languages written right to left unless a script subtag says otherwise
+const RTL_LANGUAGES: &[&str] = &[
+    "ar", "arc", "ckb", "dv", "fa", "he", "iw", "ks", "ps", "sd", "syr", "ug", "ur", "yi",
+];
+
+// script subtags of right to left scripts
+const RTL_SCRIPTS: &[&str] = &["adlm", "arab", "hebr", "nkoo", "rohg", "syrc", "thaa"];
+
+/// Html `dir` value for a BCP 47 code: "rtl", "ltr", or "auto" for unknown language.
+///
+/// ```
+/// use make_parallel_text::*;
+///
+/// assert_eq!(direction("ar"), "rtl");
+/// assert_eq!(direction("az-Arab"), "rtl");
+/// assert_eq!(direction("en"), "ltr");
+/// assert_eq!(direction("und"), "auto");
+/// ```
+pub fn direction(lang: &str) -> &'static str {
+    if lang == UNKNOWN_LANGUAGE {
+        return "auto";
+    }
+    let mut subtags = lang.split(['-', '_']).map(str::to_lowercase);
+    let primary = subtags.next().unwrap_or_default();
+    let rtl = match subtags.find(|subtag| subtag.len() == 4) {
+        Some(script) => RTL_SCRIPTS.contains(&script.as_str()),
+        None => RTL_LANGUAGES.contains(&primary.as_str()),
+    };
+    if rtl {
+        "rtl"
+    } else {
+        "ltr"
+    }
+}
+
+/// Whether a character belongs to Hebrew, Arabic, Syriac, Thaana, N'Ko or their presentation forms.
+pub fn is_rtl_char(c: char) -> bool {
+    matches!(c, '\u{0590}'..='\u{08FF}' | '\u{FB1D}'..='\u{FDFF}' | '\u{FE70}'..='\u{FEFF}')
+}
@@ -1,6 +1,69 @@
+#[cfg(feature = "model")]
 mod align;
+mod aligner;
+mod anchors;
+mod anki;
+mod confidence;
+mod epub;
+mod error;
+mod export;
+mod html;
+mod language;
+mod manifest;
+mod markdown;
+mod matrix;
+mod options;
+mod outputs;
+mod pdf;
+mod render;
+mod report;
 mod search;
+#[cfg(feature = "model")]
 mod similarity;
-pub use align::*;
-pub use search::*;
-pub use similarity::*;
+mod split;
+mod state;
+mod stream;
+mod tmx;
+
+// aligning
+#[cfg(feature = "model")]
+pub use aligner::Aligner;
+pub use aligner::{
+    joined_path, remove_backtracks, splice_path, window_shape, AlignEvent, Alignment,
+    DEFAULT_WINDOW_SIZE,
+};
+pub use anchors::{anchors_from_file, next_anchor};
+pub use error::{AlignError, AlignResult};
+pub use split::{sentences_from_file, split_into_sentences};
+
+// single window
+#[cfg(feature = "model")]
+pub use align::{alignment_path, AlignContext, WindowAlignment};
+pub use search::{cells_to_extend_diagonal, find_path};
+#[cfg(feature = "model")]
+pub use similarity::Similarity;
+
+// state dir
+pub use manifest::{verify_inputs, verify_state, Manifest, MODEL_ID};
+pub use matrix::{decode_matrix, encode_matrix, load_matrix};
+pub use state::{read_json_valid, window_files, write_atomic, write_json_atomic, WindowFile};
+
+// outputs
+pub use anki::AnkiWriter;
+pub use confidence::Similarities;
+pub use export::{Table, TableWriter};
+pub use html::{
+    block_indices, escape_html, get_sequence, inline_markup_html, plain_text, HtmlWriter, LRPair,
+    Layout, ReaderWriter, Template,
+};
+pub use language::{detect_language, direction, UNKNOWN_LANGUAGE};
+pub use markdown::MarkdownWriter;
+pub use options::{
+    Colormap, ColumnKind, Format, Inputs, OutputOptions, Separator, Side, DEFAULT_CHAPTER_PATTERN,
+    DEFAULT_FORMATS,
+};
+pub use outputs::write_outputs;
+pub use render::render_log;
+pub use report::{inspect, produce_report};
+pub use stream::{write_blocks, Block, BlockWriter};
+pub use tmx::TmxWriter;
@@ -1,251 +1,157 @@
-mod align;
+mod config;
 mod context;
-mod html;
-mod search;
-mod similarity;
-mod split;
+mod progress;
 
-use align::*;
 use anyhow::*;
-use clap::Parser;
-use glob::glob;
-use html::*;
-use regex::Regex;
-use search::*;
-use similarity::*;
-use split::*;
-use std::fs::File;
-use std::io::BufReader;
-use std::path::Path;
+use clap::{CommandFactory, FromArgMatches};
+use config::*;
+use make_parallel_text::*;
+use progress::*;
 
+#[cfg(feature = "model")]
 use tch::Device;
 
-fn sentences_from_file(file: &str) -> Result<Vec<String>> {
-    let text = std::fs::read_to_string(file)?;
-    Ok(split_into_sentences(&text))
+fn main() -> Result<()> {
+    let matches = context::Cli::command()
+        .get_matches_from(arguments_with_config(std::env::args().collect())?);
+    let cli = context::Cli::from_arg_matches(&matches).unwrap_or_else(|error| error.exit());
+
+    match cli.command {
+        #[cfg(feature = "model")]
+        Some(context::Command::Align(args)) => align(&args.context, &args.output),
+        Some(context::Command::Render(render)) => {
+            render_state(&render.inputs.inputs(), &render.output.options())
+        }
+        Some(context::Command::Export(export)) => {
+            render_state(&export.inputs.inputs(), &export.output.options())
+        }
+        Some(context::Command::Inspect(inspect)) => inspect_state(&inspect.inputs.inputs()),
+        #[cfg(feature = "model")]
+        Some(context::Command::Realign(realign)) => realign_region(&realign),
+        Some(context::Command::RenderLog(args)) => {
+            let mut progress = Progress::new(None);
+            render_log(
+                &args.context,
+                args.scale,
+                args.colormap,
+                args.force,
+                &mut |event| progress.event(event),
+            )
+        }
+        // clap requires alignment arguments when there is no command
+        #[cfg(feature = "model")]
+        None => align(&cli.context.unwrap(), &cli.output),
+        // and a command without the model
+        #[cfg(not(feature = "model"))]
+        None => unreachable!(),
+    }?;
+    persist_settings(&matches)
 }
 
-fn produce_html_from_paths(context: &context::Context) -> Result<()> {
-    let left_sentences = sentences_from_file(&context.left)?;
-    let right_sentences = sentences_from_file(&context.right)?;
+// Outputs from existing alignment, nothing is embedded.
+fn render_state(inputs: &Inputs, output: &OutputOptions) -> Result<()> {
+    verify_inputs(inputs)?;
+    let left_sentences = sentences_from_file(&inputs.left)?;
+    let right_sentences = sentences_from_file(&inputs.right)?;
+    let mut progress = Progress::new(None);
+    write_outputs(
+        inputs,
+        output,
+        &left_sentences,
+        &right_sentences,
+        &mut |event| progress.event(event),
+    )
+}
 
-    let path = joined_path(&context)?;
-    let result_file = context.context.clone() + "/result.json";
-    let data = (
-        path.clone(),
-        left_sentences.clone(),
-        right_sentences.clone(),
+fn inspect_state(inputs: &Inputs) -> Result<()> {
+    verify_inputs(inputs)?;
+    let left_sentences = sentences_from_file(&inputs.left)?;
+    let right_sentences = sentences_from_file(&inputs.right)?;
+    let path = joined_path(&inputs.context)?;
+    print!(
+        "{}",
+        inspect(&inputs.context, &left_sentences, &right_sentences, &path)?
     );
-    std::fs::write(&result_file, serde_json::to_string(&data).unwrap()).unwrap();
-
-    let html = produce_html(&left_sentences, &right_sentences, &path, 3);
-    let file = context.context.clone() + "/3-columns.html";
-    std::fs::write(&file, html).unwrap();
-
-    let html = produce_html(&left_sentences, &right_sentences, &path, 2);
-    let file = context.context.clone() + "/2-columns.html";
-    std::fs::write(&file, html).unwrap();
-
-    let html = produce_html(&left_sentences, &right_sentences, &path, 1);
-    let file = context.context.clone() + "/1-column.html";
-    std::fs::write(&file, html).unwrap();
-
     Ok(())
 }
 
-fn joined_path(context: &context::Context) -> Result<Vec<(usize, usize)>> {
-    // Compile the regex to capture X, Y, Z from filenames like "path-X-Y-Z.json"
-    let re = Regex::new(r"path-(\d+)-(\d+)-(\d+)\.json$")?;
-
-    // A small struct to hold the filename parts + the actual path
-    #[derive(Debug)]
-    struct FileMeta {
-        path: String,
-        x: usize,
-        y: usize,
-        z: usize,
-    }
-
-    // Collect all files matching the pattern (using a wildcard).
-    // Adjust the pattern to your actual directory if needed, e.g. "some_dir/path-*-*-*.json"
-    let mut files = Vec::new();
-    let pattern = context.context.clone() + "/path-*-*-*.json";
-    for entry in glob(&pattern)? {
-        let path_str = entry?.to_string_lossy().to_string();
-        if let Some(filename) = Path::new(&path_str).file_name().and_then(|s| s.to_str()) {
-            // Use the regex to parse X, Y, Z
-            if let Some(caps) = re.captures(filename) {
-                let x = caps[1].parse::<usize>()?;
-                let y = caps[2].parse::<usize>()?;
-                let z = caps[3].parse::<usize>()?;
-                files.push(FileMeta {
-                    path: path_str,
-                    x,
-                    y,
-                    z,
-                });
-            }
+// Re-aligns given ranges with both ends fixed and stores it as overlay for the joined path,
+// windows found by `align` stay as they are.
+#[cfg(feature = "model")]
+fn realign_region(realign: &context::Realign) -> Result<()> {
+    eprintln!("Device: {:?}", Device::cuda_if_available());
+    let inputs = realign.inputs.inputs();
+    verify_state(&inputs, realign.window_size, false)?;
+
+    let left_sentences = sentences_from_file(&inputs.left)?;
+    let right_sentences = sentences_from_file(&inputs.right)?;
+
+    let path = joined_path(&inputs.context)?;
+    for point in [
+        (realign.left_range.0, realign.right_range.0),
+        (realign.left_range.1, realign.right_range.1),
+    ] {
+        if !path.contains(&point) {
+            eprintln!(
+                "warning: current alignment does not go through {:?}, result may skip sentences around it",
+                point
+            );
         }
     }
 
-    // Sort by X ascending
-    files.sort_by(|a, b| a.x.cmp(&b.x));
-
-    // This will store the final joined vector
-    let mut all_data: Vec<(usize, usize)> = Vec::new();
-
-    // Process each file in sorted order
-    for file_meta in files {
-        let file = File::open(&file_meta.path)?;
-        let reader = BufReader::new(file);
-
-        // The file contains Vec<(usize, usize)>
-        let data: Vec<(usize, usize)> = serde_json::from_reader(reader)?;
-
-        // Apply the Y and Z deltas. If the item is (a, b), then the updated item is (a + Y, b + Z).
-        let updated_data = data
-            .into_iter()
-            .map(|(a, b)| (a + file_meta.y, b + file_meta.z))
-            .collect::<Vec<_>>();
-
-        // Push into the final collection
-        all_data.extend(updated_data);
-    }
-
-    // overlaps caused by half-window movement between each path found
-    let all_data = remove_backtracks(&all_data);
-
-    Ok(all_data)
+    let path = Aligner::new().state_dir(&inputs.context).realign(
+        &left_sentences,
+        &right_sentences,
+        realign.left_range,
+        realign.right_range,
+    )?;
+    eprintln!("=> found path of {} steps", path.len());
+
+    let mut progress = Progress::new(None);
+    write_outputs(
+        &inputs,
+        &realign.output.options(),
+        &left_sentences,
+        &right_sentences,
+        &mut |event| progress.event(event),
+    )
 }
 
-/// Removes branches in a path (represented by `(usize, usize)` coordinates) that
-/// were invalidated by backtracking.
-///
-/// # Example
-///
-/// ```
-/// // Suppose we have a path where (2,0) gets revisited, causing everything
-/// // after the *first* (2,0) to be truncated before continuing.
-/// let input = vec![
-///     (0,0),
-///     (1,0),
-///     (2,0),
-///     (3,0),
-///     (2,0), // backtrack to the point at index 2
-///     (2,1),
-///     (2,2),
-///     (2,0), // backtrack again to the point at index 2 (in the truncated path)
-///     (2,3)
-/// ];
-///
-/// let output = remove_backtracks(&input);
-/// assert_eq!(output, vec![
-///     (0,0),
-///     (1,0),
-///     (2,0),
-///     (2,3)  // all invalidated branches between the old (2,0) and this new (2,0) got removed
-/// ]);
-/// ```
-pub fn remove_backtracks(path: &[(usize, usize)]) -> Vec<(usize, usize)> {
-    let mut result = Vec::new();
-
-    for &p in path {
-        // Check if `p` already appears in `result`; if so, truncate everything
-        // after its *first* occurrence. This effectively discards the old path
-        // from that point forward, replacing it with the “new” path.
-        if let Some(i) = result.iter().position(|&q| q == p) {
-            result.truncate(i);
-        }
-        // Now push the current point onto our canonical path
-        result.push(p);
+#[cfg(feature = "model")]
+fn align(context: &context::Context, output: &context::OutputArgs) -> Result<()> {
+    eprintln!("Device: {:?}", Device::cuda_if_available());
+    let discarded = verify_state(
+        &context.inputs(),
+        context.window_size,
+        context.force_restart,
+    )?;
+    if !discarded.is_empty() {
+        eprintln!("=> discarding state: {}", discarded.join(", "));
     }
 
-    result
-}
-
-fn main() -> Result<()> {
-    println!("Device: {:?}", Device::cuda_if_available());
-    let context = context::Context::parse();
-
     let left_sentences = sentences_from_file(&context.left)?;
     let right_sentences = sentences_from_file(&context.right)?;
 
-    let ctx = AlignContext::new();
-
-    let mut iteration = 0;
-
-    // for iteration
-    let mut left_start: usize = 0;
-    let mut right_start: usize = 0;
-
-    // 4090 seem to fit 1000-1500, but dijkstra is too slow above 500
-    //let score_batch = 300 as usize;
-    let score_batch = context.window_size as usize;
-
-    loop {
-        println!("iteration: {}...", iteration);
-        let flexible_start = iteration == 0;
-
-        let path_file_name = format!(
-            "{}/path-{}-{}-{}.json",
-            context.context, iteration, left_start, right_start
-        );
-        let path = if std::fs::metadata(path_file_name.clone()).is_ok() {
-            println!("=> skipped");
-            let path: Vec<(usize, usize)> =
-                serde_json::from_str(&std::fs::read_to_string(path_file_name).unwrap()).unwrap();
-            path
-        } else {
-            let left_xs: Vec<&str> = left_sentences
-                .iter()
-                .skip(left_start as usize)
-                .take(score_batch.into())
-                .map(|s| s.as_str())
-                .collect();
-            let right_xs: Vec<&str> = right_sentences
-                .iter()
-                .skip(right_start as usize)
-                .take(score_batch.into())
-                .map(|s| s.as_str())
-                .collect();
-
-            let (path, similarity_matrix) =
-                alignment_path(&left_xs, &right_xs, flexible_start, &ctx);
-
-            std::fs::write(
-                format!(
-                    "{}/matrix-{}-{}-{}.json",
-                    context.context, iteration, left_start, right_start
-                ),
-                serde_json::to_string(&similarity_matrix).unwrap(),
-            )
-            .unwrap();
-
-            std::fs::write(path_file_name, serde_json::to_string(&path).unwrap()).unwrap();
-            println!("=> found path of {} steps", path.len());
-            path
-        };
-
-        // reached any border is exit condition
-        if let Some(last) = path.last() {
-            if left_start + last.0 + 1 == left_sentences.len()
-                || right_start + last.1 + 1 == right_sentences.len()
-            {
-                break;
-            }
-        } else {
-            panic!("ain't");
-        }
-
-        let mid = path.get(path.len() / 2).unwrap();
-
-        left_start += mid.0;
-        right_start += mid.1;
-
-        iteration += 1;
-    }
-
-    produce_html_from_paths(&context)?;
-
-    Ok(())
+    let anchors = match &context.anchors {
+        Some(file) => anchors_from_file(file, &left_sentences, &right_sentences)?,
+        None => vec![],
+    };
+
+    let mut progress = Progress::new(context.events);
+    let alignment = Aligner::new()
+        .window_size(context.window_size)
+        .anchors(anchors)
+        .state_dir(&context.context)
+        .save_matrices(!context.no_matrices)
+        .align_sentences_with(left_sentences, right_sentences, &mut |event| {
+            progress.event(event)
+        })?;
+
+    write_outputs(
+        &context.inputs(),
+        &output.options(),
+        &alignment.left_sentences,
+        &alignment.right_sentences,
+        &mut |event| progress.event(event),
+    )
 }
@@ -0,0 +1,191 @@
+use crate::error::{AlignError, AlignResult};
+use crate::options::Inputs;
+use crate::split::SPLITTER_ID;
+use crate::state::{state_files, window_files, write_atomic};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Sentence embedding model of `Similarity`, recorded in the manifest.
+pub const MODEL_ID: &str = "distiluse-base-multilingual-cased";
+
+/// Everything that makes paths in the state dir valid for the inputs.
+/// Anchors and realigned ranges are not here, their changes are picked up without restart.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct Manifest {
+    pub left_sha256: String,
+    pub right_sha256: String,
+    pub model: String,
+    pub splitter: String,
+    pub window_size: usize,
+}
+
+impl Manifest {
+    pub fn new(inputs: &Inputs, window_size: usize) -> AlignResult<Self> {
+        Ok(Self {
+            left_sha256: file_hash(&inputs.left)?,
+            right_sha256: file_hash(&inputs.right)?,
+            model: MODEL_ID.to_string(),
+            splitter: SPLITTER_ID.to_string(),
+            window_size,
+        })
+    }
+
+    /// What changed from this manifest to `other`, empty when state made for it is still valid.
+    fn differences(&self, other: &Manifest) -> Vec<String> {
+        let mut result = vec![];
+        if self.left_sha256 != other.left_sha256 {
+            result.push("left text has changed".to_string());
+        }
+        if self.right_sha256 != other.right_sha256 {
+            result.push("right text has changed".to_string());
+        }
+        if self.model != other.model {
+            result.push(format!("model: {} -> {}", self.model, other.model));
+        }
+        if self.splitter != other.splitter {
+            result.push(format!("splitter: {} -> {}", self.splitter, other.splitter));
+        }
+        if self.window_size != other.window_size {
+            result.push(format!(
+                "window size: {} -> {}",
+                self.window_size, other.window_size
+            ));
+        }
+        result
+    }
+}
+
+pub fn file_hash(file: &str) -> AlignResult<String> {
+    let bytes = std::fs::read(file).map_err(AlignError::io(file))?;
+    Ok(format!("{:x}", Sha256::digest(&bytes)))
+}
+
+fn manifest_file(dir: &str) -> String {
+    dir.to_string() + "/manifest.json"
+}
+
+fn read_manifest<T: DeserializeOwned>(file: &str) -> AlignResult<T> {
+    let text = std::fs::read_to_string(file).map_err(AlignError::io(file))?;
+    serde_json::from_str(&text).map_err(|e| AlignError::StateCorrupted {
+        file: file.to_string(),
+        reason: e.to_string(),
+    })
+}
+
+/// Makes sure the state dir was produced for the same inputs and settings.
+///
+/// Fresh state dir gets the manifest written,
+/// mismatching one is refused unless `force_restart`, then it is cleaned up
+/// and the differences it was discarded for are returned.
+pub fn verify_state(
+    inputs: &Inputs,
+    window_size: usize,
+    force_restart: bool,
+) -> AlignResult<Vec<String>> {
+    let current = Manifest::new(inputs, window_size)?;
+    let file = manifest_file(&inputs.context);
+
+    let problems = if std::fs::metadata(&file).is_ok() {
+        let stored: Manifest = read_manifest(&file)?;
+        stored.differences(&current)
+    } else if has_state_files(&inputs.context)? {
+        vec!["state has no manifest, it was made by an older version".to_string()]
+    } else {
+        vec![]
+    };
+
+    if !problems.is_empty() {
+        if !force_restart {
+            return Err(AlignError::StateMismatch {
+                dir: inputs.context.clone(),
+                problems,
+            });
+        }
+        clean_state(&inputs.context)?;
+    }
+
+    let json = serde_json::to_string_pretty(&current).map_err(|e| AlignError::Io {
+        file: file.clone(),
+        source: e.into(),
+    })?;
+    write_atomic(&file, json)?;
+    Ok(problems)
+}
+
+const STATE_PATTERNS: &[&str] = &[
+    "path-*-*-*.json",
+    "matrix-*-*-*.json",
+    "matrix-*-*-*.bin",
+    "realign-*-*-*-*.json",
+    "result.json",
+];
+
+fn has_state_files(dir: &str) -> AlignResult<bool> {
+    for pattern in STATE_PATTERNS {
+        if !state_files(dir, pattern)?.is_empty() {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+fn clean_state(dir: &str) -> AlignResult<()> {
+    for pattern in STATE_PATTERNS {
+        for file in state_files(dir, pattern)? {
+            std::fs::remove_file(&file).map_err(AlignError::io(&file.to_string_lossy()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Makes sure the state dir was aligned for these texts, before using it without aligning.
+///
+/// State without manifest can not be checked and is trusted.
+pub fn verify_inputs(inputs: &Inputs) -> AlignResult<()> {
+    if window_files(&inputs.context)?.is_empty() {
+        return Err(AlignError::NotAligned(inputs.context.clone()));
+    }
+
+    let file = manifest_file(&inputs.context);
+    if std::fs::metadata(&file).is_err() {
+        return Ok(());
+    }
+    let stored: Manifest = read_manifest(&file)?;
+    if stored.left_sha256 != file_hash(&inputs.left)?
+        || stored.right_sha256 != file_hash(&inputs.right)?
+    {
+        return Err(AlignError::OtherTexts(inputs.context.clone()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(right_sha256: &str, window_size: usize) -> Manifest {
+        Manifest {
+            left_sha256: "aa".to_string(),
+            right_sha256: right_sha256.to_string(),
+            model: MODEL_ID.to_string(),
+            splitter: SPLITTER_ID.to_string(),
+            window_size,
+        }
+    }
+
+    #[test]
+    fn same_manifest_has_no_differences() {
+        assert!(manifest("bb", 300)
+            .differences(&manifest("bb", 300))
+            .is_empty());
+    }
+
+    #[test]
+    fn differences_name_changed_inputs_and_settings() {
+        assert_eq!(
+            manifest("bb", 300).differences(&manifest("cc", 200)),
+            vec!["right text has changed", "window size: 300 -> 200"]
+        );
+    }
+}
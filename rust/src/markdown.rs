@@ -0,0 +1,122 @@
+use crate::html::{plain_text, range_text, Layout};
+use crate::options::{ColumnKind, OutputOptions};
+use crate::stream::{Block, BlockWriter};
+use anyhow::*;
+use std::io::Write;
+
+/// Interlinear Markdown: a paragraph per block, main text first and the rest of
+/// `--interlinear-order` under it in italics.
+pub struct MarkdownWriter<'a, W: Write> {
+    out: W,
+    kinds: Vec<ColumnKind>,
+    args: &'a OutputOptions,
+    title: &'a str,
+}
+
+impl<'a, W: Write> MarkdownWriter<'a, W> {
+    pub fn new(out: W, args: &'a OutputOptions, title: &'a str) -> Self {
+        Self {
+            out,
+            kinds: Layout::Interlinear(args.interlinear_order.clone()).kinds(args),
+            args,
+            title,
+        }
+    }
+}
+
+impl<W: Write> BlockWriter for MarkdownWriter<'_, W> {
+    fn begin(&mut self) -> Result<()> {
+        writeln!(self.out, "# {}", escape_markdown(self.title))?;
+        Ok(())
+    }
+
+    fn block(&mut self, block: &Block) -> Result<()> {
+        let args = self.args;
+        let text = |sentences: &mut dyn Iterator<Item = &String>| {
+            sentences
+                .map(|s| plain_text(s, args.inline_markup))
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+
+        let lines: Vec<String> = self
+            .kinds
+            .iter()
+            .filter_map(|&kind| {
+                let line = match kind {
+                    ColumnKind::Left => text(&mut block.left_texts()),
+                    ColumnKind::Right => text(&mut block.right_texts()),
+                    ColumnKind::Notes => return None,
+                    ColumnKind::Index => format!(
+                        "{}: {} / {}",
+                        block.index,
+                        range_text(&block.left),
+                        range_text(&block.right)
+                    ),
+                    ColumnKind::Similarity => format!("{:.2}", block.similarity?),
+                };
+                Some(escape_markdown(&line))
+            })
+            .filter(|line| !line.is_empty())
+            .enumerate()
+            .map(|(i, line)| if i == 0 { line } else { format!("*{}*", line) })
+            .collect();
+
+        // backslash at line end is a hard line break of CommonMark
+        write!(self.out, "\n{}\n", lines.join("\\\n"))?;
+        Ok(())
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+// Markdown syntax characters are escaped so that text is shown as is,
+// a line starting like a list item or a heading is not one.
+fn escape_markdown(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(
+            c,
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '#' | '|' | '~'
+        ) {
+            result.push('\\');
+        }
+        result.push(c);
+    }
+
+    if result.starts_with(['-', '+', '=']) {
+        result.insert(0, '\\');
+    }
+    // "12. text" would be an ordered list item
+    let digits = result.chars().take_while(char::is_ascii_digit).count();
+    if digits > 0 && result[digits..].starts_with(['.', ')']) {
+        result.insert(digits, '\\');
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn syntax_characters_are_escaped() {
+        assert_eq!(
+            escape_markdown("*not* a_b [link]"),
+            r"\*not\* a\_b \[link\]"
+        );
+        assert_eq!(escape_markdown("# title | x"), r"\# title \| x");
+        assert_eq!(escape_markdown("plain text"), "plain text");
+    }
+
+    #[test]
+    fn line_starts_are_not_lists() {
+        assert_eq!(escape_markdown("- item"), r"\- item");
+        assert_eq!(escape_markdown("12. twelve"), r"12\. twelve");
+        assert_eq!(escape_markdown("3) three"), r"3\) three");
+        assert_eq!(escape_markdown("1984 was"), "1984 was");
+    }
+}
@@ -0,0 +1,88 @@
+use std::io::{Error, ErrorKind, Result};
+
+// Binary similarity matrix, little endian:
+//
+// magic "MPTM" | version u8 | encoding u8 | rows u32 | cols u32 | rows * cols values
+//
+// encoding 0: similarity -1..1 quantized into u8 0..255 (same as grayscale of the images),
+// precision is about 0.008 which is plenty for inspecting and scoring paths.
+const MAGIC: &[u8; 4] = b"MPTM";
+const VERSION: u8 = 1;
+const ENCODING_U8: u8 = 0;
+const HEADER_SIZE: usize = 14;
+
+/// Encodes similarity matrix (rows of -1..1 values) into compact binary form.
+///
+/// ```
+/// use make_parallel_text::*;
+///
+/// let matrix = vec![vec![1.0, 0.0, -1.0], vec![0.5, -0.5, 0.25]];
+/// let bytes = encode_matrix(&matrix);
+/// assert_eq!(bytes.len(), 14 + 6);
+///
+/// let decoded = decode_matrix(&bytes).unwrap();
+/// assert_eq!(decoded.len(), 2);
+/// for (row, decoded_row) in matrix.iter().zip(&decoded) {
+///     for (a, b) in row.iter().zip(decoded_row) {
+///         assert!((a - b).abs() < 0.01);
+///     }
+/// }
+/// ```
+pub fn encode_matrix(matrix: &[Vec<f32>]) -> Vec<u8> {
+    let rows = matrix.len();
+    let cols = matrix.first().map_or(0, |row| row.len());
+
+    let mut bytes = Vec::with_capacity(HEADER_SIZE + rows * cols);
+    bytes.extend_from_slice(MAGIC);
+    bytes.push(VERSION);
+    bytes.push(ENCODING_U8);
+    bytes.extend_from_slice(&(rows as u32).to_le_bytes());
+    bytes.extend_from_slice(&(cols as u32).to_le_bytes());
+
+    for row in matrix {
+        bytes.extend(
+            row.iter()
+                .map(|value| ((value.clamp(-1., 1.) + 1.) * 127.5).round() as u8),
+        );
+    }
+
+    bytes
+}
+
+/// Decodes matrix written by `encode_matrix`, fails on anything else or truncated data.
+pub fn decode_matrix(bytes: &[u8]) -> Result<Vec<Vec<f32>>> {
+    let invalid = |message: &str| Error::new(ErrorKind::InvalidData, message.to_string());
+
+    if bytes.len() < HEADER_SIZE || &bytes[0..4] != MAGIC {
+        return Err(invalid("not a similarity matrix"));
+    }
+    if bytes[4] != VERSION || bytes[5] != ENCODING_U8 {
+        return Err(invalid("unsupported similarity matrix version"));
+    }
+
+    let rows = u32::from_le_bytes(bytes[6..10].try_into().unwrap()) as usize;
+    let cols = u32::from_le_bytes(bytes[10..14].try_into().unwrap()) as usize;
+    let data = &bytes[HEADER_SIZE..];
+    if data.len() != rows * cols {
+        return Err(invalid("similarity matrix is truncated"));
+    }
+    if cols == 0 {
+        return Ok(vec![vec![]; rows]);
+    }
+
+    Ok(data
+        .chunks(cols)
+        .map(|row| row.iter().map(|&v| v as f32 / 127.5 - 1.).collect())
+        .collect())
+}
+
+/// Reads similarity matrix file: binary `.bin` or JSON one written by older versions.
+pub fn load_matrix(file: &str) -> Result<Vec<Vec<f32>>> {
+    let bytes = std::fs::read(file)?;
+
+    if file.ends_with(".json") {
+        serde_json::from_slice(&bytes).map_err(|e| Error::new(ErrorKind::InvalidData, e))
+    } else {
+        decode_matrix(&bytes)
+    }
+}
@@ -0,0 +1,199 @@
+use crate::language::UNKNOWN_LANGUAGE;
+use clap::ValueEnum;
+
+/// Regex of sentences starting a chapter, sentences are lowercase.
+pub const DEFAULT_CHAPTER_PATTERN: &str = r"^(chapter|part|book|chapitre|kapitel|capítulo|capitolo|глава|часть|книга)\b[^.!?]{0,40}[.!?]?$|^([ivxlc]+|\d+)\.?$";
+
+/// Texts and state dir of an existing alignment.
+#[derive(Debug, Clone)]
+pub struct Inputs {
+    pub left: String,
+    pub right: String,
+    pub context: String,
+}
+
+/// Look and metadata of output files, the command line sets them with options of the same names.
+///
+/// ```
+/// use make_parallel_text::*;
+///
+/// let options = OutputOptions {
+///     formats: vec![Format::Epub, Format::Tmx],
+///     left_lang: "en".to_string(),
+///     ..OutputOptions::default()
+/// };
+/// assert_eq!(options.right_lang, UNKNOWN_LANGUAGE);
+/// ```
+#[derive(Debug, Clone)]
+pub struct OutputOptions {
+    /// outputs to write, `DEFAULT_FORMATS` when empty
+    pub formats: Vec<Format>,
+    /// write the only one of `formats` to standard output instead of a file
+    pub stdout: bool,
+    /// keep italics and bold tags of input text in html, otherwise any markup is shown as text
+    pub inline_markup: bool,
+    /// CSS page size, "A4 landscape" for columns and "A4" for one column when not given
+    pub page_size: Option<String>,
+    pub font_family: String,
+    pub font_size: String,
+    /// relative widths of text and notes columns in their order
+    pub column_widths: Vec<u32>,
+    /// put right text first: into the left column, on top in one column layout
+    pub swap_sides: bool,
+    /// columns of custom-columns.html in their order
+    pub columns: Vec<ColumnKind>,
+    /// what one column layout shows of each block, top to bottom
+    pub stack_order: Vec<ColumnKind>,
+    /// what interlinear layout shows of each block: main text first, then smaller lines under it
+    pub interlinear_order: Vec<ColumnKind>,
+    /// interlinear blocks run one after another like words of a paragraph
+    pub interlinear_flow: bool,
+    pub separator: Separator,
+    /// html file used instead of built-in one
+    pub template: Option<String>,
+    /// css file added after built-in style
+    pub css: Option<String>,
+    /// left file name when not given
+    pub left_title: Option<String>,
+    pub right_title: Option<String>,
+    /// BCP 47 code, `UNKNOWN_LANGUAGE` is detected from the text
+    pub left_lang: String,
+    pub right_lang: String,
+    /// TrueType font embedded into pdf, `DEFAULT_FONT` when not given
+    pub font: Option<String>,
+    /// blocks with lower mean similarity are left out of exports
+    pub min_similarity: Option<f32>,
+    pub anki_front: Side,
+    /// longest block for anki cards, in sentences of either side
+    pub anki_max_sentences: usize,
+    /// longest text of anki card side, in characters
+    pub anki_max_chars: usize,
+    pub chapter_pattern: String,
+}
+
+impl Default for OutputOptions {
+    fn default() -> Self {
+        Self {
+            formats: vec![],
+            stdout: false,
+            inline_markup: false,
+            page_size: None,
+            font_family: "Arial, sans-serif".to_string(),
+            font_size: "18pt".to_string(),
+            column_widths: vec![1, 1, 1],
+            swap_sides: false,
+            columns: vec![],
+            stack_order: vec![ColumnKind::Right, ColumnKind::Left],
+            interlinear_order: vec![ColumnKind::Left, ColumnKind::Right],
+            interlinear_flow: false,
+            separator: Separator::Line,
+            template: None,
+            css: None,
+            left_title: None,
+            right_title: None,
+            left_lang: UNKNOWN_LANGUAGE.to_string(),
+            right_lang: UNKNOWN_LANGUAGE.to_string(),
+            font: None,
+            min_similarity: None,
+            anki_front: Side::Left,
+            anki_max_sentences: 2,
+            anki_max_chars: 200,
+            chapter_pattern: DEFAULT_CHAPTER_PATTERN.to_string(),
+        }
+    }
+}
+
+impl OutputOptions {
+    /// Language of the text that comes first, it is the language of a document as a whole.
+    pub fn main_lang(&self) -> &str {
+        if self.swap_sides {
+            &self.right_lang
+        } else {
+            &self.left_lang
+        }
+    }
+
+    /// Language of the other text.
+    pub fn second_lang(&self) -> &str {
+        if self.swap_sides {
+            &self.left_lang
+        } else {
+            &self.right_lang
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+/// Outputs written when none are selected, the three html files, the overview image and the report.
+pub const DEFAULT_FORMATS: &[Format] = &[
+    Format::ThreeColumns,
+    Format::TwoColumns,
+    Format::OneColumn,
+    Format::Overview,
+    Format::Report,
+];
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    #[value(name = "3-columns")]
+    ThreeColumns,
+    #[value(name = "2-columns")]
+    TwoColumns,
+    #[value(name = "1-column")]
+    OneColumn,
+    /// columns of --columns
+    CustomColumns,
+    Interlinear,
+    InterlinearMd,
+    Reader,
+    Epub,
+    #[value(name = "2-columns-pdf")]
+    TwoColumnsPdf,
+    #[value(name = "3-columns-pdf")]
+    ThreeColumnsPdf,
+    Tmx,
+    Tsv,
+    Csv,
+    Jsonl,
+    Anki,
+    Overview,
+    Report,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum ColumnKind {
+    /// left text
+    Left,
+    /// right text
+    Right,
+    /// empty space for notes
+    Notes,
+    /// block number and sentence ranges
+    Index,
+    /// mean similarity of the block, it needs saved matrices
+    Similarity,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum Separator {
+    /// horizontal line
+    Line,
+    /// blank space
+    Space,
+    None,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum Colormap {
+    /// black is -1 similarity, white is 1, path is yellow
+    Gray,
+    /// perceptually uniform blue-green-yellow, path is red
+    Viridis,
+    /// black-red-yellow-white, path is cyan
+    Heat,
+}
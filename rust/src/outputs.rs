@@ -0,0 +1,275 @@
+use crate::aligner::{joined_path, AlignEvent};
+use crate::anki::AnkiWriter;
+use crate::confidence::Similarities;
+use crate::epub::{book_titles, chapter_headings, EpubOut, EpubWriter};
+use crate::export::{Table, TableWriter};
+use crate::html::{get_sequence, HtmlWriter, Layout, ReaderWriter, Template};
+use crate::language::with_languages;
+use crate::language::UNKNOWN_LANGUAGE;
+use crate::markdown::MarkdownWriter;
+use crate::options::{ColumnKind, Format, Inputs, OutputOptions, DEFAULT_FORMATS};
+use crate::pdf::{load_font, produce_pdf};
+use crate::render::render_overview;
+use crate::report::produce_report;
+use crate::state::write_json_atomic;
+use crate::stream::{file_name, write_blocks, BlockWriter};
+use crate::tmx::TmxWriter;
+use anyhow::*;
+use std::fs::File;
+use std::io::{Cursor, Write};
+
+/// Writes result.json and outputs selected by `formats` into the state dir,
+/// or only one of them to stdout.
+///
+/// Path is joined from the state dir, so it has to be aligned before.
+/// Detected languages are told through `on_event`.
+pub fn write_outputs(
+    inputs: &Inputs,
+    output: &OutputOptions,
+    left_sentences: &[String],
+    right_sentences: &[String],
+    on_event: &mut dyn FnMut(&AlignEvent),
+) -> Result<()> {
+    let path = joined_path(&inputs.context)?;
+    if !output.stdout {
+        let result_file = inputs.context.clone() + "/result.json";
+        write_json_atomic(&result_file, &(&path, left_sentences, right_sentences))?;
+    }
+
+    let given = output;
+    let formats = match &output.formats[..] {
+        [] => DEFAULT_FORMATS,
+        formats => formats,
+    };
+    let wanted = |format: Format| formats.contains(&format);
+    if output.stdout {
+        match formats {
+            [Format::Overview | Format::Report] => {
+                bail!("overview and report can not be written to standard output")
+            }
+            [_] => {}
+            _ => bail!("--stdout needs exactly one output in --formats"),
+        }
+    }
+    if wanted(Format::CustomColumns) && output.columns.is_empty() {
+        bail!("custom-columns output needs --columns");
+    }
+    // missing font is found before anything is written
+    let pdfs = [(Format::TwoColumnsPdf, 2), (Format::ThreeColumnsPdf, 3)];
+    let font = if pdfs.iter().any(|&(format, _)| wanted(format)) {
+        Some(load_font(output)?)
+    } else {
+        None
+    };
+
+    let output = &with_languages(output, left_sentences, right_sentences);
+    for (side, given, lang) in [
+        ("left", &given.left_lang, &output.left_lang),
+        ("right", &given.right_lang, &output.right_lang),
+    ] {
+        if given == UNKNOWN_LANGUAGE && lang != UNKNOWN_LANGUAGE {
+            on_event(&AlignEvent::Notice {
+                message: format!("{} text language: {}", side, lang),
+            });
+        }
+    }
+    let (left_title, right_title) = book_titles(inputs, output);
+    let title = format!("{} / {}", left_title, right_title);
+    let template = Template::load(output)?;
+
+    let similarities = Similarities::from_state(&inputs.context, &path)?;
+    let file = |name: &str| format!("{}/{}", inputs.context, name);
+    let destination = |format: Format| -> Result<Box<dyn std::io::Write>> {
+        if output.stdout {
+            return Ok(Box::new(std::io::BufWriter::new(std::io::stdout())));
+        }
+        let file = file(file_name(format));
+        let out = File::create(&file).with_context(|| format!("can not write {}", file))?;
+        Ok(Box::new(std::io::BufWriter::new(out)))
+    };
+
+    // everything that is written block by block goes in one pass
+    let sequence = get_sequence(&path);
+    let headings = chapter_headings(&sequence, left_sentences, right_sentences, output)?;
+    let chapters: Vec<usize> = headings.iter().map(|&(block, _)| block).collect();
+    let layouts = [
+        (
+            Format::ThreeColumns,
+            Layout::Columns(vec![ColumnKind::Left, ColumnKind::Right, ColumnKind::Notes]),
+        ),
+        (
+            Format::TwoColumns,
+            Layout::Columns(vec![ColumnKind::Left, ColumnKind::Right]),
+        ),
+        (
+            Format::OneColumn,
+            Layout::Stacked(output.stack_order.clone()),
+        ),
+        (
+            Format::Interlinear,
+            Layout::Interlinear(output.interlinear_order.clone()),
+        ),
+        (
+            Format::CustomColumns,
+            Layout::Columns(output.columns.clone()),
+        ),
+    ];
+
+    let mut writers: Vec<Box<dyn BlockWriter>> = vec![];
+    for (format, layout) in &layouts {
+        if wanted(*format) {
+            let out = destination(*format)?;
+            writers.push(Box::new(HtmlWriter::new(out, layout, output, &template)));
+        }
+    }
+    if wanted(Format::Reader) {
+        writers.push(Box::new(ReaderWriter::new(
+            destination(Format::Reader)?,
+            &path,
+            output,
+            &template,
+            &left_title,
+            &right_title,
+        )));
+    }
+    if wanted(Format::InterlinearMd) {
+        let out = destination(Format::InterlinearMd)?;
+        writers.push(Box::new(MarkdownWriter::new(out, output, &title)));
+    }
+    if wanted(Format::Tmx) {
+        writers.push(Box::new(TmxWriter::new(destination(Format::Tmx)?, output)));
+    }
+    for (format, table) in [
+        (Format::Tsv, Table::Tsv),
+        (Format::Csv, Table::Csv),
+        (Format::Jsonl, Table::Jsonl),
+    ] {
+        if wanted(format) {
+            writers.push(Box::new(TableWriter::new(
+                destination(format)?,
+                table,
+                output,
+            )));
+        }
+    }
+    if wanted(Format::Anki) {
+        let out = destination(Format::Anki)?;
+        writers.push(Box::new(AnkiWriter::new(out, &chapters, output, &title)));
+    }
+    if wanted(Format::Epub) {
+        let out = if output.stdout {
+            EpubOut::Memory(Cursor::default(), destination(Format::Epub)?)
+        } else {
+            let file = file(file_name(Format::Epub));
+            EpubOut::File(File::create(&file).with_context(|| format!("can not write {}", file))?)
+        };
+        writers.push(Box::new(EpubWriter::new(
+            out,
+            inputs,
+            output,
+            &headings,
+            sequence.len(),
+        )));
+    }
+    write_blocks(
+        left_sentences,
+        right_sentences,
+        &sequence,
+        Some(&similarities),
+        &mut writers,
+    )?;
+
+    let write_bytes = |format: Format, bytes: &[u8]| -> Result<()> {
+        let mut out = destination(format)?;
+        out.write_all(bytes)?;
+        out.flush()?;
+        Ok(())
+    };
+
+    if let Some(font) = &font {
+        for (format, columns) in pdfs {
+            if !wanted(format) {
+                continue;
+            }
+            let pdf = produce_pdf(
+                left_sentences,
+                right_sentences,
+                &path,
+                columns,
+                output,
+                &title,
+                font,
+            )?;
+            write_bytes(format, &pdf)?;
+        }
+    }
+
+    if wanted(Format::Overview) {
+        render_overview(
+            &inputs.context,
+            left_sentences.len(),
+            right_sentences.len(),
+            &path,
+            &file(file_name(Format::Overview)),
+        )?;
+    }
+
+    if wanted(Format::Report) {
+        // first of written pages that have block anchors
+        let blocks_page = [
+            Format::TwoColumns,
+            Format::ThreeColumns,
+            Format::CustomColumns,
+            Format::Reader,
+            Format::Interlinear,
+        ]
+        .into_iter()
+        .find(|&format| wanted(format))
+        .map(file_name);
+        produce_report(
+            &inputs.context,
+            left_sentences,
+            right_sentences,
+            &path,
+            blocks_page,
+        )?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_run_writes_default_outputs() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let context = dir.path().to_string_lossy().to_string();
+        std::fs::write(dir.path().join("path-0-0-0.json"), "[[0, 0], [1, 1]]")?;
+        let inputs = Inputs {
+            left: "left.txt".to_string(),
+            right: "right.txt".to_string(),
+            context,
+        };
+        let sentences = |side: &str| vec![format!("One {}.", side), format!("Two {}.", side)];
+
+        write_outputs(
+            &inputs,
+            &OutputOptions::default(),
+            &sentences("left"),
+            &sentences("right"),
+            &mut |_| {},
+        )?;
+
+        for file in [
+            "result.json",
+            "3-columns.html",
+            "overview.png",
+            "report.html",
+        ] {
+            assert!(dir.path().join(file).exists(), "{} is not written", file);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,490 @@
+use crate::html::{block_indices, column_percents, get_sequence, plain_text};
+use crate::language::{direction, is_rtl_char};
+use crate::options::{OutputOptions, Separator};
+use anyhow::Context as _;
+use anyhow::*;
+use pdf_writer::types::{CidFontType, FontFlags, SystemInfo};
+use pdf_writer::{Content, Finish, Name, Pdf, Rect, Ref, Str, TextStr};
+use std::collections::BTreeMap;
+
+/// Font used when `--font` is not given (fonts-dejavu-core package).
+pub const DEFAULT_FONT: &str = "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf";
+
+/// Font file contents for pdf: `--font` or the default one.
+pub fn load_font(args: &OutputOptions) -> Result<Vec<u8>> {
+    match &args.font {
+        Some(file) => std::fs::read(file).with_context(|| format!("can not read font {}", file)),
+        None => std::fs::read(DEFAULT_FONT).with_context(|| {
+            format!(
+                "no font at {}, pdf needs one: install DejaVu Sans or give --font file.ttf",
+                DEFAULT_FONT
+            )
+        }),
+    }
+}
+
+// in font sizes
+const LEADING: f32 = 1.25;
+const MARGIN: f32 = 1.;
+const GUTTER: f32 = 0.6;
+const BLOCK_GAP: f32 = 0.6;
+
+/// TrueType font embedded as a whole, text is drawn by glyph ids.
+struct Font<'a> {
+    data: &'a [u8],
+    face: ttf_parser::Face<'a>,
+    // glyphs used so far with their text, for widths and copy-paste of the document
+    used: BTreeMap<u16, char>,
+}
+
+impl<'a> Font<'a> {
+    fn new(data: &'a [u8]) -> Result<Self> {
+        let face = ttf_parser::Face::parse(data, 0).map_err(|e| anyhow!("{}", e))?;
+        Ok(Self {
+            data,
+            face,
+            used: BTreeMap::new(),
+        })
+    }
+
+    fn glyph(&self, c: char) -> u16 {
+        self.face.glyph_index(c).map_or(0, |g| g.0)
+    }
+
+    // advance in thousandths of font size, units of PDF glyph widths
+    fn advance(&self, glyph: u16) -> f32 {
+        let advance = self
+            .face
+            .glyph_hor_advance(ttf_parser::GlyphId(glyph))
+            .unwrap_or(0);
+        self.scale(advance as f32)
+    }
+
+    fn scale(&self, units: f32) -> f32 {
+        units * 1000. / self.face.units_per_em() as f32
+    }
+
+    fn width(&self, text: &str, size: f32) -> f32 {
+        text.chars()
+            .map(|c| self.advance(self.glyph(c)))
+            .sum::<f32>()
+            * size
+            / 1000.
+    }
+
+    // Identity-H encoded string: big endian glyph ids.
+    fn encode(&mut self, text: &str) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(text.len() * 2);
+        for c in text.chars() {
+            let glyph = self.glyph(c);
+            self.used.entry(glyph).or_insert(c);
+            bytes.extend_from_slice(&glyph.to_be_bytes());
+        }
+        bytes
+    }
+
+    fn postscript_name(&self) -> String {
+        self.face
+            .names()
+            .into_iter()
+            .filter(|name| name.name_id == ttf_parser::name_id::POST_SCRIPT_NAME)
+            .find_map(|name| name.to_string())
+            .unwrap_or_else(|| "Font".to_string())
+            .replace(|c: char| !c.is_ascii_alphanumeric() && c != '-', "")
+    }
+}
+
+/// Page geometry and text metrics of a layout, in points.
+struct Layout {
+    width: f32,
+    height: f32,
+    size: f32,
+    columns: Vec<(f32, f32)>,
+}
+
+impl Layout {
+    fn new(args: &OutputOptions, columns: usize) -> Result<Self> {
+        let (width, height) = page_size(args.page_size.as_deref().unwrap_or("A4 landscape"))?;
+        let size = font_size(&args.font_size)?;
+
+        let margin = MARGIN * size;
+        let gutter = GUTTER * size;
+        let available = width - 2. * margin - gutter * (columns - 1) as f32;
+
+        let mut x = margin;
+        let columns = column_percents(args, columns)
+            .into_iter()
+            .map(|percent| {
+                let column = (x, available * percent / 100.);
+                x += column.1 + gutter;
+                column
+            })
+            .collect();
+
+        Ok(Self {
+            width,
+            height,
+            size,
+            columns,
+        })
+    }
+
+    fn leading(&self) -> f32 {
+        self.size * LEADING
+    }
+
+    fn top(&self) -> f32 {
+        self.height - MARGIN * self.size
+    }
+
+    fn bottom(&self) -> f32 {
+        MARGIN * self.size
+    }
+}
+
+/// Writes PDF with aligned blocks in two columns, or three with the last one blank for notes.
+///
+/// A block is moved to the next page rather than broken, unless it is longer than a page.
+/// Unlike other outputs the document is built whole in memory, pdf-writer has no streaming.
+pub fn produce_pdf(
+    left_sentences: &[String],
+    right_sentences: &[String],
+    path: &[(usize, usize)],
+    columns: usize,
+    args: &OutputOptions,
+    title: &str,
+    font_data: &[u8],
+) -> Result<Vec<u8>> {
+    let layout = Layout::new(args, columns)?;
+    let mut font = Font::new(font_data)?;
+    let text = |sentence: &String| plain_text(sentence, args.inline_markup);
+
+    let directions = if args.swap_sides {
+        [direction(&args.right_lang), direction(&args.left_lang)]
+    } else {
+        [direction(&args.left_lang), direction(&args.right_lang)]
+    };
+
+    let mut pages: Vec<Content> = vec![Content::new()];
+    let mut y = layout.top();
+
+    for (index, block) in get_sequence(path).iter().enumerate() {
+        let (ls, rs) = block_indices(block);
+        let left: Vec<String> = ls
+            .iter()
+            .filter_map(|&i| left_sentences.get(i))
+            .map(text)
+            .collect();
+        let right: Vec<String> = rs
+            .iter()
+            .filter_map(|&i| right_sentences.get(i))
+            .map(text)
+            .collect();
+        let (first, second) = if args.swap_sides {
+            (right, left)
+        } else {
+            (left, right)
+        };
+
+        let lines: Vec<Vec<String>> = [first, second]
+            .iter()
+            .zip(&layout.columns)
+            .map(|(sentences, &(_, width))| {
+                sentences
+                    .iter()
+                    .flat_map(|sentence| wrap(&font, sentence, layout.size, width))
+                    .collect()
+            })
+            .collect();
+        let rows = lines.iter().map(Vec::len).max().unwrap_or(0);
+
+        // separator goes between blocks, not at page tops
+        if index > 0 && y < layout.top() {
+            let gap = match args.separator {
+                Separator::None => 0.,
+                _ => BLOCK_GAP * layout.size,
+            };
+            if y - gap - layout.leading() < layout.bottom() {
+                pages.push(Content::new());
+                y = layout.top();
+            } else {
+                if let Separator::Line = args.separator {
+                    let line_y = y - gap / 2.;
+                    let page = pages.last_mut().unwrap();
+                    page.set_stroke_gray(0.6)
+                        .set_line_width(0.5)
+                        .move_to(layout.bottom(), line_y)
+                        .line_to(layout.width - layout.bottom(), line_y)
+                        .stroke();
+                }
+                y -= gap;
+            }
+        }
+
+        // keep block on one page when it fits on an empty one
+        let block_height = rows as f32 * layout.leading();
+        let page_height = layout.top() - layout.bottom();
+        if y - block_height < layout.bottom() && block_height <= page_height && y < layout.top() {
+            pages.push(Content::new());
+            y = layout.top();
+        }
+
+        for row in 0..rows {
+            if y - layout.leading() < layout.bottom() {
+                pages.push(Content::new());
+                y = layout.top();
+            }
+            let baseline = y - layout.size;
+            let page = pages.last_mut().unwrap();
+            for ((column_lines, &(x, width)), dir) in
+                lines.iter().zip(&layout.columns).zip(directions)
+            {
+                if let Some(line) = column_lines.get(row) {
+                    let rtl = match dir {
+                        "rtl" => true,
+                        "ltr" => false,
+                        _ => line
+                            .chars()
+                            .find(|c| c.is_alphabetic())
+                            .is_some_and(is_rtl_char),
+                    };
+                    // right to left lines are right aligned and drawn in visual order
+                    let (x, line) = if rtl {
+                        (
+                            x + width - font.width(line, layout.size),
+                            visual_order(line),
+                        )
+                    } else {
+                        (x, line.clone())
+                    };
+                    page.begin_text()
+                        .set_font(Name(b"F1"), layout.size)
+                        .next_line(x, baseline)
+                        .show(Str(&font.encode(&line)))
+                        .end_text();
+                }
+            }
+            y -= layout.leading();
+        }
+    }
+
+    write_pdf(pages, &layout, &mut font, title)
+}
+
+fn write_pdf(
+    pages: Vec<Content>,
+    layout: &Layout,
+    font: &mut Font,
+    title: &str,
+) -> Result<Vec<u8>> {
+    let mut pdf = Pdf::new();
+    let mut next = Ref::new(1);
+    let mut alloc = || next.bump();
+
+    let catalog_id = alloc();
+    let tree_id = alloc();
+    let info_id = alloc();
+    let font_id = alloc();
+    let cid_font_id = alloc();
+    let descriptor_id = alloc();
+    let font_file_id = alloc();
+    let to_unicode_id = alloc();
+    let page_ids: Vec<(Ref, Ref)> = pages.iter().map(|_| (alloc(), alloc())).collect();
+
+    pdf.catalog(catalog_id).pages(tree_id);
+    pdf.pages(tree_id)
+        .kids(page_ids.iter().map(|ids| ids.0))
+        .count(page_ids.len() as i32);
+    pdf.document_info(info_id).title(TextStr(title));
+
+    for (content, &(page_id, content_id)) in pages.into_iter().zip(&page_ids) {
+        let mut page = pdf.page(page_id);
+        page.media_box(Rect::new(0., 0., layout.width, layout.height));
+        page.parent(tree_id);
+        page.contents(content_id);
+        page.resources().fonts().pair(Name(b"F1"), font_id);
+        page.finish();
+        pdf.stream(content_id, &content.finish());
+    }
+
+    let name = font.postscript_name();
+    let system_info = SystemInfo {
+        registry: Str(b"Adobe"),
+        ordering: Str(b"Identity"),
+        supplement: 0,
+    };
+
+    pdf.type0_font(font_id)
+        .base_font(Name(name.as_bytes()))
+        .encoding_predefined(Name(b"Identity-H"))
+        .descendant_font(cid_font_id)
+        .to_unicode(to_unicode_id);
+
+    let mut cid_font = pdf.cid_font(cid_font_id);
+    cid_font
+        .subtype(CidFontType::Type2)
+        .base_font(Name(name.as_bytes()))
+        .system_info(system_info)
+        .font_descriptor(descriptor_id)
+        .cid_to_gid_map_predefined(Name(b"Identity"));
+    let mut widths = cid_font.widths();
+    for &glyph in font.used.keys() {
+        widths.consecutive(glyph, [font.advance(glyph)]);
+    }
+    widths.finish();
+    cid_font.finish();
+
+    let face = &font.face;
+    let bbox = face.global_bounding_box();
+    pdf.font_descriptor(descriptor_id)
+        .name(Name(name.as_bytes()))
+        .flags(FontFlags::SYMBOLIC)
+        .bbox(Rect::new(
+            font.scale(bbox.x_min as f32),
+            font.scale(bbox.y_min as f32),
+            font.scale(bbox.x_max as f32),
+            font.scale(bbox.y_max as f32),
+        ))
+        .italic_angle(face.italic_angle().unwrap_or(0.))
+        .ascent(font.scale(face.ascender() as f32))
+        .descent(font.scale(face.descender() as f32))
+        .cap_height(font.scale(face.capital_height().unwrap_or(face.ascender()) as f32))
+        .stem_v(80.)
+        .font_file2(font_file_id);
+
+    pdf.stream(font_file_id, font.data)
+        .pair(Name(b"Length1"), font.data.len() as i32);
+
+    let mut cmap = pdf_writer::types::UnicodeCmap::new(Name(b"Custom"), system_info);
+    for (&glyph, &c) in &font.used {
+        cmap.pair(glyph, c);
+    }
+    pdf.cmap(to_unicode_id, &cmap.finish());
+
+    Ok(pdf.finish())
+}
+
+// Right to left line as it is drawn from left to right: words in reverse order,
+// letters of right to left words reversed with brackets mirrored, numbers and latin
+// words kept as they are. There is no shaping, Arabic letters are drawn in their isolated forms.
+fn visual_order(line: &str) -> String {
+    let mirror = |c: char| match c {
+        '(' => ')',
+        ')' => '(',
+        '[' => ']',
+        ']' => '[',
+        '{' => '}',
+        '}' => '{',
+        '«' => '»',
+        '»' => '«',
+        c => c,
+    };
+    line.split(' ')
+        .rev()
+        .map(|word| {
+            if word.chars().any(is_rtl_char) {
+                word.chars().rev().map(mirror).collect()
+            } else {
+                word.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// Greedy line breaking at spaces, words longer than a line are broken anywhere.
+fn wrap(font: &Font, text: &str, size: f32, width: f32) -> Vec<String> {
+    let mut lines = vec![];
+    let mut line = String::new();
+    // widths add up glyph by glyph, so the line is measured as it grows
+    let mut line_width = 0.;
+    let space = font.width(" ", size);
+
+    for word in text.split_whitespace() {
+        let word_width = font.width(word, size);
+        if line.is_empty() && word_width <= width {
+            line.push_str(word);
+            line_width = word_width;
+            continue;
+        }
+        if !line.is_empty() && line_width + space + word_width <= width {
+            line.push(' ');
+            line.push_str(word);
+            line_width += space + word_width;
+            continue;
+        }
+        if !line.is_empty() {
+            lines.push(std::mem::take(&mut line));
+            line_width = 0.;
+        }
+        if word_width <= width {
+            line.push_str(word);
+            line_width = word_width;
+            continue;
+        }
+        for c in word.chars() {
+            let c_width = font.width(c.encode_utf8(&mut [0; 4]), size);
+            if !line.is_empty() && line_width + c_width > width {
+                lines.push(std::mem::take(&mut line));
+                line_width = 0.;
+            }
+            line.push(c);
+            line_width += c_width;
+        }
+    }
+    if !line.is_empty() || lines.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+// Page size in points from the same value as --page-size of html:
+// a name like "A5", optionally with "landscape"/"portrait", or width and height like "12in 9in".
+fn page_size(value: &str) -> Result<(f32, f32)> {
+    let named = |name: &str| match name {
+        "a3" => Some((842., 1191.)),
+        "a4" => Some((595., 842.)),
+        "a5" => Some((420., 595.)),
+        "a6" => Some((298., 420.)),
+        "letter" => Some((612., 792.)),
+        "legal" => Some((612., 1008.)),
+        _ => None,
+    };
+
+    let words: Vec<String> = value.split_whitespace().map(str::to_lowercase).collect();
+    let size = match words.as_slice() {
+        [name] => named(name),
+        [name, orientation] if orientation == "portrait" => named(name),
+        [name, orientation] if orientation == "landscape" => named(name).map(|(w, h)| (h, w)),
+        [width, height] => Some((length(width)?, length(height)?)),
+        _ => None,
+    };
+    size.ok_or_else(|| anyhow!("unsupported page size for pdf: {}", value))
+}
+
+fn length(value: &str) -> Result<f32> {
+    let units = [
+        ("mm", 72. / 25.4),
+        ("cm", 72. / 2.54),
+        ("in", 72.),
+        ("pt", 1.),
+        ("px", 0.75),
+    ];
+    let (number, scale) = units
+        .iter()
+        .find_map(|&(unit, scale)| value.strip_suffix(unit).map(|number| (number, scale)))
+        .unwrap_or((value, 1.));
+    let number: f32 = number
+        .trim()
+        .parse()
+        .with_context(|| format!("invalid length {}", value))?;
+    Ok(number * scale)
+}
+
+fn font_size(value: &str) -> Result<f32> {
+    let size = length(value)?;
+    if size <= 0. {
+        bail!("invalid font size {}", value);
+    }
+    Ok(size)
+}
@@ -0,0 +1,130 @@
+use crate::context::Events;
+use indicatif::{HumanDuration, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use make_parallel_text::AlignEvent;
+use std::io::IsTerminal;
+use std::time::Duration;
+
+/// Shows progress of aligning on standard error: a bar of sentences covered on both sides with ETA,
+/// or a line per window when it is not a terminal (logs of Docker runs).
+/// With `--events json` every event also goes to standard output as a JSON line.
+pub struct Progress {
+    bar: ProgressBar,
+    lines: bool,
+    events: Option<Events>,
+    left_total: usize,
+    right_total: usize,
+    covered: usize,
+    // windows computed in this run, windows done before take no time and tell nothing of speed
+    computed_seconds: f64,
+    computed_sentences: usize,
+}
+
+impl Progress {
+    pub fn new(events: Option<Events>) -> Self {
+        let lines = !std::io::stderr().is_terminal();
+        let target = if lines {
+            ProgressDrawTarget::hidden()
+        } else {
+            ProgressDrawTarget::stderr()
+        };
+        let bar = ProgressBar::with_draw_target(None, target);
+        bar.set_style(
+            ProgressStyle::with_template(
+                "{spinner} window {prefix} [{elapsed_precise}] {wide_bar} {msg}",
+            )
+            .expect("valid template"),
+        );
+
+        Self {
+            bar,
+            lines,
+            events,
+            left_total: 0,
+            right_total: 0,
+            covered: 0,
+            computed_seconds: 0.0,
+            computed_sentences: 0,
+        }
+    }
+
+    pub fn event(&mut self, event: &AlignEvent) {
+        if self.events == Some(Events::Json) {
+            if let Ok(line) = serde_json::to_string(event) {
+                println!("{}", line);
+            }
+        }
+
+        match *event {
+            AlignEvent::Started {
+                left_sentences,
+                right_sentences,
+                ..
+            } => {
+                self.left_total = left_sentences;
+                self.right_total = right_sentences;
+                self.bar
+                    .set_length((left_sentences + right_sentences) as u64);
+                self.bar.enable_steady_tick(Duration::from_millis(200));
+            }
+            AlignEvent::IterationStarted { iteration, .. } => {
+                self.bar.set_prefix(iteration.to_string());
+            }
+            AlignEvent::IterationFinished {
+                iteration,
+                steps,
+                skipped,
+                seconds,
+                left_covered,
+                right_covered,
+                ..
+            } => {
+                let covered = left_covered + right_covered;
+                if !skipped {
+                    self.computed_seconds += seconds;
+                    self.computed_sentences += covered.saturating_sub(self.covered);
+                }
+                self.covered = self.covered.max(covered);
+                self.bar.set_position(self.covered as u64);
+
+                let status = format!(
+                    "left {}/{}, right {}/{} sentences, ETA {}",
+                    left_covered,
+                    self.left_total,
+                    right_covered,
+                    self.right_total,
+                    self.eta()
+                );
+                if self.lines {
+                    let found = if skipped {
+                        "done before".to_string()
+                    } else {
+                        format!("found path of {} steps", steps)
+                    };
+                    eprintln!("window {}: {}, {}", iteration, found, status);
+                }
+                self.bar.set_message(status);
+            }
+            AlignEvent::Notice { ref message } => {
+                self.bar.suspend(|| eprintln!("=> {}", message));
+            }
+            AlignEvent::Finished { steps, seconds, .. } => {
+                self.bar.finish_and_clear();
+                eprintln!(
+                    "=> aligned in {}, path of {} steps",
+                    HumanDuration(Duration::from_secs_f64(seconds)),
+                    steps
+                );
+            }
+        }
+    }
+
+    // remaining sentences at the speed of windows computed so far
+    fn eta(&self) -> String {
+        if self.computed_sentences == 0 {
+            return "unknown yet".to_string();
+        }
+        let remaining = (self.left_total + self.right_total).saturating_sub(self.covered);
+        let seconds = self.computed_seconds * remaining as f64 / self.computed_sentences as f64;
+        HumanDuration(Duration::from_secs_f64(seconds)).to_string()
+    }
+}
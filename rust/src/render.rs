@@ -0,0 +1,250 @@
+use crate::aligner::AlignEvent;
+use crate::matrix::load_matrix;
+use crate::options::Colormap;
+use crate::state::window_files;
+use anyhow::*;
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::BufWriter;
+
+/// Renders similarity matrix of every window in `dir` into `{iteration}.png`
+/// and the same with found path highlighted into `{iteration}-path.png`,
+/// `scale` pixels per matrix cell. Windows that have images are skipped unless `force`.
+pub fn render_log(
+    dir: &str,
+    scale: u32,
+    colormap: Colormap,
+    force: bool,
+    on_event: &mut dyn FnMut(&AlignEvent),
+) -> Result<()> {
+    let scale = scale.max(1);
+
+    for window in window_files(dir)? {
+        let output = format!("{}/{}.png", dir, window.iteration);
+        let output_path = format!("{}/{}-path.png", dir, window.iteration);
+        if !force && std::fs::metadata(&output_path).is_ok() {
+            continue;
+        }
+
+        let Some(matrix_file) = window.matrix_file() else {
+            on_event(&AlignEvent::Notice {
+                message: format!(
+                    "no matrix for iteration {} (--no-matrices?)",
+                    window.iteration
+                ),
+            });
+            continue;
+        };
+        let matrix = load_matrix(&matrix_file)?;
+        let path: HashSet<(usize, usize)> = window.path()?.into_iter().collect();
+
+        render_matrix_image(&matrix, &HashSet::new(), scale, colormap, &output)?;
+        render_matrix_image(&matrix, &path, scale, colormap, &output_path)?;
+        on_event(&AlignEvent::Notice {
+            message: output_path,
+        });
+    }
+
+    Ok(())
+}
+
+// Matrix rows are right sentences and columns are left ones, path is (left, right) pairs.
+fn render_matrix_image(
+    matrix: &[Vec<f32>],
+    path: &HashSet<(usize, usize)>,
+    scale: u32,
+    colormap: Colormap,
+    image_file: &str,
+) -> Result<()> {
+    let scale = scale as usize;
+    let height = matrix.len() * scale;
+    let width = matrix.first().map_or(0, |row| row.len()) * scale;
+
+    let mut pixels = vec![0; width * height * 3];
+    for (y, row) in matrix.iter().enumerate() {
+        for (x, &value) in row.iter().enumerate() {
+            let color = if path.contains(&(x, y)) {
+                colormap.highlight(value)
+            } else {
+                colormap.color(value)
+            };
+
+            for py in y * scale..(y + 1) * scale {
+                for px in x * scale..(x + 1) * scale {
+                    let at = (py * width + px) * 3;
+                    pixels[at..at + 3].copy_from_slice(&color);
+                }
+            }
+        }
+    }
+
+    write_png(image_file, width as u32, height as u32, &pixels)
+}
+
+/// Writes RGB pixels (row by row, 3 bytes each) as PNG image.
+pub fn write_png(file: &str, width: u32, height: u32, pixels: &[u8]) -> Result<()> {
+    let out = BufWriter::new(File::create(file)?);
+
+    let mut encoder = png::Encoder::new(out, width, height);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(pixels)?;
+    writer.finish()?;
+    Ok(())
+}
+
+impl Colormap {
+    /// Color of similarity value -1..1.
+    pub fn color(self, value: f32) -> [u8; 3] {
+        let t = ((value.clamp(-1., 1.) + 1.) / 2.) as f64;
+
+        match self {
+            Colormap::Gray => {
+                let v = (t * 255.) as u8;
+                [v, v, v]
+            }
+            Colormap::Viridis => gradient(
+                &[
+                    [68, 1, 84],
+                    [59, 82, 139],
+                    [33, 145, 140],
+                    [94, 201, 98],
+                    [253, 231, 37],
+                ],
+                t,
+            ),
+            Colormap::Heat => {
+                gradient(&[[0, 0, 0], [180, 0, 0], [255, 160, 0], [255, 255, 255]], t)
+            }
+        }
+    }
+
+    /// Color of path cell with similarity value -1..1, it has to stand out from `color`.
+    pub fn highlight(self, value: f32) -> [u8; 3] {
+        match self {
+            Colormap::Gray => {
+                let [v, _, _] = self.color(value);
+                [v, v, 0]
+            }
+            Colormap::Viridis => [255, 40, 40],
+            Colormap::Heat => [0, 200, 255],
+        }
+    }
+}
+
+// linear interpolation between evenly spaced color stops, t is 0..1
+fn gradient(stops: &[[u8; 3]], t: f64) -> [u8; 3] {
+    let at = t * (stops.len() - 1) as f64;
+    let i = (at.floor() as usize).min(stops.len() - 2);
+    let k = at - i as f64;
+
+    let mut color = [0; 3];
+    for c in 0..3 {
+        let a = stops[i][c] as f64;
+        let b = stops[i + 1][c] as f64;
+        color[c] = (a + (b - a) * k).round() as u8;
+    }
+    color
+}
+
+// overview is downsampled so that its longer side is about this size
+const OVERVIEW_SIZE: usize = 1000;
+const NO_DATA: [u8; 3] = [225, 228, 240];
+const WINDOW_BORDER: [u8; 3] = [60, 120, 255];
+const DISCARDED_PATH: [u8; 3] = [255, 60, 0];
+const JOINED_PATH: [u8; 3] = [0, 210, 0];
+
+/// Renders the joined path over the whole left (x) by right (y) sentence space.
+///
+/// Background is similarity from saved window matrices, windows are outlined
+/// and parts of window paths that were discarded by following overlapping windows are marked.
+pub fn render_overview(
+    dir: &str,
+    left_len: usize,
+    right_len: usize,
+    joined: &[(usize, usize)],
+    image_file: &str,
+) -> Result<()> {
+    let step = left_len.max(right_len).div_ceil(OVERVIEW_SIZE).max(1);
+    let width = left_len.div_ceil(step).max(1);
+    let height = right_len.div_ceil(step).max(1);
+    let pixel =
+        |l: usize, r: usize| ((r / step).min(height - 1) * width + (l / step).min(width - 1)) * 3;
+
+    // similarity averaged over sentence pairs falling into every pixel
+    let mut sums = vec![0f32; width * height];
+    let mut counts = vec![0u32; width * height];
+    // window rectangles as (left_start, right_start, left_end, right_end), ends inclusive
+    let mut windows = vec![];
+    let mut window_points = HashSet::new();
+
+    for window in window_files(dir)? {
+        let path = window.path()?;
+        let (mut left_end, mut right_end) = path
+            .iter()
+            .fold((0, 0), |(a, b), &(l, r)| (a.max(l), b.max(r)));
+
+        if let Some(matrix_file) = window.matrix_file() {
+            let matrix = load_matrix(&matrix_file)?;
+            for (y, row) in matrix.iter().enumerate() {
+                for (x, &value) in row.iter().enumerate() {
+                    let at = pixel(window.left_start + x, window.right_start + y) / 3;
+                    sums[at] += value;
+                    counts[at] += 1;
+                }
+            }
+            right_end = matrix.len().saturating_sub(1);
+            left_end = matrix.first().map_or(0, |row| row.len().saturating_sub(1));
+        }
+
+        windows.push((
+            window.left_start,
+            window.right_start,
+            window.left_start + left_end,
+            window.right_start + right_end,
+        ));
+        window_points.extend(
+            path.iter()
+                .map(|&(l, r)| (l + window.left_start, r + window.right_start)),
+        );
+    }
+
+    let mut pixels = vec![0; width * height * 3];
+    for (at, (&sum, &count)) in sums.iter().zip(&counts).enumerate() {
+        let color = if count > 0 {
+            Colormap::Gray.color(sum / count as f32)
+        } else {
+            NO_DATA
+        };
+        pixels[at * 3..at * 3 + 3].copy_from_slice(&color);
+    }
+
+    for &(l0, r0, l1, r1) in &windows {
+        for l in (l0..=l1).step_by(step) {
+            for r in [r0, r1] {
+                let at = pixel(l, r);
+                pixels[at..at + 3].copy_from_slice(&WINDOW_BORDER);
+            }
+        }
+        for r in (r0..=r1).step_by(step) {
+            for l in [l0, l1] {
+                let at = pixel(l, r);
+                pixels[at..at + 3].copy_from_slice(&WINDOW_BORDER);
+            }
+        }
+    }
+
+    let joined_points: HashSet<(usize, usize)> = joined.iter().copied().collect();
+    for &(l, r) in window_points.difference(&joined_points) {
+        let at = pixel(l, r);
+        pixels[at..at + 3].copy_from_slice(&DISCARDED_PATH);
+    }
+    for &(l, r) in joined {
+        let at = pixel(l, r);
+        pixels[at..at + 3].copy_from_slice(&JOINED_PATH);
+    }
+
+    write_png(image_file, width as u32, height as u32, &pixels)
+}
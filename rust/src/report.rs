@@ -0,0 +1,372 @@
+use crate::confidence::{mean, path_similarity, Similarities};
+use crate::html::{block_indices, escape_html, get_sequence};
+use crate::matrix::load_matrix;
+use crate::state::window_files;
+use anyhow::*;
+use std::cmp::Reverse;
+use std::fmt::Write;
+
+const HEADER: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+  <meta charset="UTF-8">
+  <title>Alignment report</title>
+  <style>
+    body { font-family: Arial, sans-serif; margin: 2em; max-width: 70em; }
+    table { border-collapse: collapse; margin-bottom: 2em; }
+    th, td { border: 1px solid #ccc; padding: 3px 8px; vertical-align: top; text-align: left; }
+    td.n { text-align: right; }
+    .bar { background: #6a8; height: 1em; }
+    .text { max-width: 30em; }
+  </style>
+</head>
+<body>
+"#;
+
+// how many rows to show in "worst" and "longest" tables
+const TOP: usize = 20;
+
+/// What both `report.html` and `inspect` show of the joined path.
+struct Summary {
+    blocks: Vec<(Vec<usize>, Vec<usize>)>,
+    left_uncovered: Vec<(usize, usize)>,
+    right_uncovered: Vec<(usize, usize)>,
+    // mean similarity of blocks that have it, worst first
+    scored: Vec<(usize, f32)>,
+}
+
+impl Summary {
+    fn new(
+        dir: &str,
+        left_sentences: &[String],
+        right_sentences: &[String],
+        path: &[(usize, usize)],
+    ) -> Result<Self> {
+        let sequence = get_sequence(path);
+        let similarities = Similarities::from_state(dir, path)?;
+
+        let mut scored: Vec<(usize, f32)> = sequence
+            .iter()
+            .enumerate()
+            .filter_map(|(index, block)| Some((index, similarities.block(block)?)))
+            .collect();
+        scored.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+        Ok(Self {
+            blocks: sequence.iter().map(|b| block_indices(b)).collect(),
+            left_uncovered: uncovered_runs(path.iter().map(|p| p.0), left_sentences.len()),
+            right_uncovered: uncovered_runs(path.iter().map(|p| p.1), right_sentences.len()),
+            scored,
+        })
+    }
+
+    // sentences not in any block, left and right
+    fn uncovered(&self) -> (usize, usize) {
+        let count = |runs: &[(usize, usize)]| runs.iter().map(|(a, b)| b - a + 1).sum::<usize>();
+        (count(&self.left_uncovered), count(&self.right_uncovered))
+    }
+
+    // count of blocks by left:right sentences, most common first
+    fn shapes(&self) -> Vec<(String, usize)> {
+        let mut shapes: Vec<(String, usize)> = vec![];
+        for (ls, rs) in &self.blocks {
+            let shape = format!("{}:{}", shape_size(ls.len()), shape_size(rs.len()));
+            match shapes.iter_mut().find(|(s, _)| *s == shape) {
+                Some((_, n)) => *n += 1,
+                None => shapes.push((shape, 1)),
+            }
+        }
+        shapes.sort_by_key(|s| Reverse(s.1));
+        shapes
+    }
+
+    fn mean_similarity(&self) -> Option<f32> {
+        mean(self.scored.iter().map(|s| s.1))
+    }
+}
+
+/// Writes `report.html` summarizing alignment quality of the joined path.
+///
+/// Worst blocks link into `blocks_page`, an html output with block anchors written along with it.
+pub fn produce_report(
+    dir: &str,
+    left_sentences: &[String],
+    right_sentences: &[String],
+    path: &[(usize, usize)],
+    blocks_page: Option<&str>,
+) -> Result<()> {
+    let summary = Summary::new(dir, left_sentences, right_sentences, path)?;
+    let blocks = &summary.blocks;
+    let block_link = |index: usize| match blocks_page {
+        Some(page) => format!("<a href=\"{}#b{1}\">#{1}</a>", page, index),
+        None => format!("#{}", index),
+    };
+
+    let mut html = HEADER.to_string();
+    html.push_str("<h1>Alignment report</h1>\n");
+
+    // -- summary
+    let (left_uncovered, right_uncovered) = summary.uncovered();
+    html.push_str("<h2>Summary</h2>\n<table>\n<tr><th></th><th>left</th><th>right</th></tr>\n");
+    writeln!(
+        html,
+        "<tr><td>sentences</td><td class=n>{}</td><td class=n>{}</td></tr>",
+        left_sentences.len(),
+        right_sentences.len()
+    )?;
+    writeln!(
+        html,
+        "<tr><td>not in any block</td><td class=n>{}</td><td class=n>{}</td></tr>",
+        left_uncovered, right_uncovered
+    )?;
+    html.push_str("</table>\n");
+
+    // -- blocks by shape
+    let shapes = summary.shapes();
+
+    writeln!(
+        html,
+        "<h2>Blocks</h2>\n<p>{} blocks, left:right sentences in a block</p>\n<table>",
+        blocks.len()
+    )?;
+    let most = shapes.first().map_or(1, |s| s.1);
+    for (shape, n) in &shapes {
+        writeln!(
+            html,
+            "<tr><td>{}</td><td class=n>{}</td><td style=\"width: 20em\"><div class=bar style=\"width: {:.1}%\"></div></td></tr>",
+            shape,
+            n,
+            *n as f32 * 100. / most as f32
+        )?;
+    }
+    writeln!(
+        html,
+        "<tr><td>N:0 runs</td><td class=n>{}</td><td></td></tr>\n<tr><td>0:N runs</td><td class=n>{}</td><td></td></tr>\n</table>",
+        summary.left_uncovered.len(),
+        summary.right_uncovered.len()
+    )?;
+
+    // -- iterations
+    html.push_str("<h2>Iterations</h2>\n<table>\n<tr><th>iteration</th><th>left start</th><th>right start</th><th>path steps</th><th>mean similarity</th></tr>\n");
+    for window in window_files(dir)? {
+        let window_path = window.path()?;
+        let mean = match window.matrix_file() {
+            Some(file) => path_similarity(&window_path, &load_matrix(&file)?),
+            None => None,
+        };
+        writeln!(
+            html,
+            "<tr><td class=n>{}</td><td class=n>{}</td><td class=n>{}</td><td class=n>{}</td><td class=n>{}</td></tr>",
+            window.iteration,
+            window.left_start,
+            window.right_start,
+            window_path.len(),
+            format_similarity(mean)
+        )?;
+    }
+    html.push_str("</table>\n");
+
+    // -- longest unaligned runs: sentences with no counterpart of their own,
+    // extra sentences of 1:N blocks and sentences left out of blocks entirely
+    let mut runs: Vec<(&str, usize, usize, Option<usize>)> = vec![];
+    for (index, (ls, rs)) in blocks.iter().enumerate() {
+        if ls.len() == 1 && rs.len() > 1 {
+            runs.push(("right", rs[1], rs[rs.len() - 1], Some(index)));
+        }
+        if rs.len() == 1 && ls.len() > 1 {
+            runs.push(("left", ls[1], ls[ls.len() - 1], Some(index)));
+        }
+    }
+    runs.extend(
+        summary
+            .left_uncovered
+            .iter()
+            .map(|&(a, b)| ("left", a, b, None)),
+    );
+    runs.extend(
+        summary
+            .right_uncovered
+            .iter()
+            .map(|&(a, b)| ("right", a, b, None)),
+    );
+    runs.sort_by_key(|run| Reverse(run.2 - run.1));
+
+    html.push_str("<h2>Longest unaligned runs</h2>\n<table>\n<tr><th>side</th><th>sentences</th><th>length</th><th>block</th></tr>\n");
+    for (side, from, to, block) in runs.iter().take(TOP) {
+        writeln!(
+            html,
+            "<tr><td>{}</td><td>{}-{}</td><td class=n>{}</td><td>{}</td></tr>",
+            side,
+            from,
+            to,
+            to - from + 1,
+            block.map_or("not in any block".to_string(), block_link)
+        )?;
+    }
+    html.push_str("</table>\n");
+
+    // -- worst blocks
+    html.push_str("<h2>Worst scoring blocks</h2>\n");
+    if summary.scored.is_empty() {
+        html.push_str("<p>no similarity matrices saved (--no-matrices)</p>\n");
+    } else {
+        writeln!(
+            html,
+            "<p>mean similarity of all scored blocks: {}</p>",
+            format_similarity(summary.mean_similarity())
+        )?;
+        html.push_str(
+            "<table>\n<tr><th>block</th><th>similarity</th><th>left</th><th>right</th></tr>\n",
+        );
+        for &(index, score) in summary.scored.iter().take(TOP) {
+            let (ls, rs) = &blocks[index];
+            writeln!(
+                html,
+                "<tr><td>{}</td><td class=n>{:.3}</td><td class=text>{}</td><td class=text>{}</td></tr>",
+                block_link(index),
+                score,
+                snippet(ls, left_sentences),
+                snippet(rs, right_sentences)
+            )?;
+        }
+        html.push_str("</table>\n");
+    }
+
+    html.push_str("</body>\n</html>\n");
+    std::fs::write(format!("{}/report.html", dir), html)?;
+
+    Ok(())
+}
+
+/// Short summary of alignment in state dir for terminal.
+pub fn inspect(
+    dir: &str,
+    left_sentences: &[String],
+    right_sentences: &[String],
+    path: &[(usize, usize)],
+) -> Result<String> {
+    let summary = Summary::new(dir, left_sentences, right_sentences, path)?;
+    let windows = window_files(dir)?;
+    let (left_uncovered, right_uncovered) = summary.uncovered();
+
+    let mut text = String::new();
+    writeln!(
+        text,
+        "sentences:         left {}, right {}",
+        left_sentences.len(),
+        right_sentences.len()
+    )?;
+    writeln!(
+        text,
+        "not in any block:  left {}, right {}",
+        left_uncovered, right_uncovered
+    )?;
+    writeln!(
+        text,
+        "path:              {} steps, {} blocks",
+        path.len(),
+        summary.blocks.len()
+    )?;
+    let shapes: Vec<String> = summary
+        .shapes()
+        .iter()
+        .map(|(shape, n)| format!("{} {}", shape, n))
+        .collect();
+    writeln!(text, "blocks by shape:   {}", shapes.join(", "))?;
+    writeln!(
+        text,
+        "windows:           {}, {} with saved matrix",
+        windows.len(),
+        windows.iter().filter(|w| w.matrix_file().is_some()).count()
+    )?;
+    writeln!(
+        text,
+        "mean similarity:   {}",
+        format_similarity(summary.mean_similarity())
+    )?;
+    let worst: Vec<String> = summary
+        .scored
+        .iter()
+        .take(5)
+        .map(|(index, similarity)| format!("#{} {:.3}", index, similarity))
+        .collect();
+    if !worst.is_empty() {
+        writeln!(text, "worst blocks:      {}", worst.join(", "))?;
+    }
+    Ok(text)
+}
+
+// 1:N blocks can be long, they are grouped
+fn shape_size(n: usize) -> String {
+    match n {
+        0..=4 => n.to_string(),
+        5..=9 => "5-9".to_string(),
+        _ => "10+".to_string(),
+    }
+}
+
+// Inclusive ranges of indices below `len` that are not in `covered`.
+fn uncovered_runs(covered: impl Iterator<Item = usize>, len: usize) -> Vec<(usize, usize)> {
+    let mut seen = vec![false; len];
+    for i in covered {
+        if let Some(x) = seen.get_mut(i) {
+            *x = true;
+        }
+    }
+
+    let mut runs = vec![];
+    let mut start = None;
+    for (i, &x) in seen.iter().enumerate() {
+        match (x, start) {
+            (false, None) => start = Some(i),
+            (true, Some(s)) => {
+                runs.push((s, i - 1));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        runs.push((s, len - 1));
+    }
+    runs
+}
+
+fn snippet(indices: &[usize], sentences: &[String]) -> String {
+    let text = indices
+        .iter()
+        .filter_map(|&i| sentences.get(i))
+        .map(|s| s.trim())
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let mut short: String = text.chars().take(200).collect();
+    if short.len() < text.len() {
+        short.push('…');
+    }
+    escape_html(&short)
+}
+
+fn format_similarity(value: Option<f32>) -> String {
+    value.map_or("n/a".to_string(), |v| format!("{:.3}", v))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_between_covered_indices() {
+        let covered = vec![0, 1, 4, 6, 12];
+        assert_eq!(
+            uncovered_runs(covered.into_iter(), 9),
+            vec![(2, 3), (5, 5), (7, 8)]
+        );
+    }
+
+    #[test]
+    fn no_runs_when_all_covered() {
+        assert_eq!(uncovered_runs(0..5, 5), vec![]);
+        assert_eq!(uncovered_runs(0..0, 0), vec![]);
+    }
+}
@@ -10,7 +10,13 @@ use std::rc::Rc;
 // i32 into isize?? or shrink instead?
 
 #[derive(Clone, Eq, Hash, Ord, PartialEq, PartialOrd, Debug)]
-struct Pos(i32, i32, Rc<Vec<Vec<OrderedFloat<f32>>>>, OrderedFloat<f32>);
+struct Pos(
+    i32,
+    i32,
+    Rc<Vec<Vec<OrderedFloat<f32>>>>,
+    OrderedFloat<f32>,
+    bool,
+);
 // asking for normal {} struct
 // could have .translate + .join for result building vs keeping it in function
 
@@ -19,51 +25,54 @@ struct Pos(i32, i32, Rc<Vec<Vec<OrderedFloat<f32>>>>, OrderedFloat<f32>);
 /// # Arguments
 ///
 /// * `matrix` - A 2D vector of `f32` representing the cost matrix.
+/// * `flexible_start` - path may start anywhere on the first row/column, otherwise at `(0, 0)`.
+/// * `flexible_end` - path may end anywhere on the last row/column, otherwise at the opposite corner.
 ///
 /// # Returns
 ///
-/// A vector of `(usize, usize)` tuples representing the coordinates of the path with the least cost.
+/// A vector of `(usize, usize)` tuples representing the coordinates of the path with the least cost,
+/// `None` if there is no way from the start to the end.
 ///
 /// # Examples
 ///
 /// ## Primitive square case
 ///
 /// ```
-/// use translate::*;
+/// use make_parallel_text::*;
 ///
 /// let matrix = vec![
 ///     vec![0.0, 1.0],
 ///     vec![1.0, 0.0],
 /// ];
-/// let path = find_path(matrix, true);
+/// let path = find_path(matrix, true, true).unwrap();
 /// assert_eq!(path, vec![(0, 0), (1, 1)]);
 /// ```
 ///
 /// ## Next square case
 ///
 /// ```
-/// use translate::*;
+/// use make_parallel_text::*;
 ///
 /// let matrix = vec![
 ///     vec![0.0, 1.0, 1.0],
 ///     vec![1.0, 0.0, 1.1],
 ///     vec![1.0, 1.0, 0.0],
 /// ];
-/// let path = find_path(matrix, true);
+/// let path = find_path(matrix, true, true).unwrap();
 /// assert_eq!(path, vec![(0, 0), (1, 1), (2,2)]);
 /// ```
 ///
 /// ## Avoids using cheap corner, uses median value so needs minimal size
 ///
 /// ```
-/// use translate::*;
+/// use make_parallel_text::*;
 ///
 /// let matrix = vec![
 ///     vec![1.0, 2.0, 2.0],
 ///     vec![2.0, 1.0, 2.0],
 ///     vec![0.0, 2.0, 1.0],
 /// ];
-/// let path = find_path(matrix, true);
+/// let path = find_path(matrix, true, true).unwrap();
 /// assert_eq!(path, vec![(0, 0), (1, 1), (2,2)]);
 /// ```
 ///
@@ -72,79 +81,91 @@ struct Pos(i32, i32, Rc<Vec<Vec<OrderedFloat<f32>>>>, OrderedFloat<f32>);
 /// matrix size cases.
 ///
 /// ```
-/// use translate::*;
+/// use make_parallel_text::*;
 ///
 /// let matrix = vec![
 ///     vec![0.0, 0.0],
 ///     vec![0.0, 0.0],
 /// ];
-/// let path = find_path(matrix, true);
+/// let path = find_path(matrix, true, true).unwrap();
 /// assert_eq!(path, vec![(0, 0), (1, 1)]);
 /// ```
 ///
 /// ```
-/// use translate::*;
+/// use make_parallel_text::*;
 ///
 /// let matrix = vec![
 ///     vec![0.0, 1.0],
 ///     vec![0.0, 0.0],
 /// ];
-/// let path = find_path(matrix, true);
+/// let path = find_path(matrix, true, true).unwrap();
 /// assert_eq!(path, vec![(0, 0), (1, 1)]);
 /// ```
 ///
 /// Legit most corner case of path
 /// ```
-/// use translate::*;
+/// use make_parallel_text::*;
 ///
 /// let matrix = vec![
 ///     vec![0.0, 0.0],
 ///     vec![1.0, 1.0],
 /// ];
-/// let path = find_path(matrix, true);
+/// let path = find_path(matrix, true, true).unwrap();
 /// assert_eq!(path, vec![(0, 0), (1, 0)]);
 /// ```
 ///
 /// Non-square case: odd case, just what it is in current calculation
 /// ```
-/// use translate::*;
+/// use make_parallel_text::*;
 ///
 /// let matrix = vec![
 ///     vec![0.0, 0.0, 0.0],
 ///     vec![1.0, 1.0, 1.0],
 /// ];
-/// let path = find_path(matrix, true);
+/// let path = find_path(matrix, true, true).unwrap();
 /// assert_eq!(path, vec![(1, 0), (2, 0)]);
 /// ```
 ///
 /// Can force it to start exactly from the corner.
 /// ```
-/// use translate::*;
+/// use make_parallel_text::*;
 ///
 /// let matrix = vec![
 ///     vec![0.0, 0.0, 0.0],
 ///     vec![1.0, 1.0, 1.0],
 /// ];
-/// let path = find_path(matrix, false);
+/// let path = find_path(matrix, false, true).unwrap();
 /// assert_eq!(path, vec![(0, 0), (1, 0), (2, 0)]);
 /// ```
 ///
+/// Can force it to end exactly in the opposite corner (used for anchors).
+/// ```
+/// use make_parallel_text::*;
+///
+/// let matrix = vec![
+///     vec![0.0, 0.0, 0.0],
+///     vec![1.0, 1.0, 1.0],
+/// ];
+/// let path = find_path(matrix, false, false).unwrap();
+/// assert_eq!(path, vec![(0, 0), (1, 0), (2, 1)]);
+/// ```
+///
 /// The bias does not affect clearly shortest path, even if not diagonal.
 /// ```
-/// use translate::*;
+/// use make_parallel_text::*;
 ///
 /// let matrix = vec![
 ///     vec![0.0, 1.0],
 ///     vec![0.0, 1.000000001],
 /// ];
-/// let path = find_path(matrix, true);
+/// let path = find_path(matrix, true, true).unwrap();
 /// assert_eq!(path, vec![(0, 0), (0, 1)]);
 /// ```
 ///
 /// ## Non-square matrix: does not favor corners too
 ///
 /// ```
-/// use translate::*;
+/// use make_parallel_text::*;
 ///
 /// let matrix = vec![
 ///     vec![2.0, 2.0, 2.0], // 2.0's make for god noise median value
@@ -154,26 +175,26 @@ struct Pos(i32, i32, Rc<Vec<Vec<OrderedFloat<f32>>>>, OrderedFloat<f32>);
 ///     vec![0.0, 1.0, 2.0],
 ///     vec![0.0, 1.0, 1.0],
 /// ];
-/// let path = find_path(matrix, true);
+/// let path = find_path(matrix, true, true).unwrap();
 /// assert_eq!(path, vec![(0, 3), (1, 4), (2,5)]);
 /// ```
 ///
 /// ## Single Row Matrix
 ///
 /// ```
-/// use translate::*;
+/// use make_parallel_text::*;
 ///
 /// let matrix = vec![
 ///     vec![1.0, 0.0, 3.0, 4.0],
 /// ];
-/// let path = find_path(matrix, true);
+/// let path = find_path(matrix, true, true).unwrap();
 /// assert_eq!(path, vec![(1, 0)]);
 /// ```
 ///
 /// ## Single Column Matrix
 ///
 /// ```
-/// use translate::*;
+/// use make_parallel_text::*;
 ///
 /// let matrix = vec![
 ///     vec![1.0],
@@ -181,26 +202,30 @@ struct Pos(i32, i32, Rc<Vec<Vec<OrderedFloat<f32>>>>, OrderedFloat<f32>);
 ///     vec![0.0],
 ///     vec![4.0],
 /// ];
-/// let path = find_path(matrix, true);
+/// let path = find_path(matrix, true, true).unwrap();
 /// assert_eq!(path, vec![(0, 2)]);
 /// ```
 ///
 /// ## Empty Matrix
 ///
 /// ```
-/// use translate::*;
+/// use make_parallel_text::*;
 ///
 /// let matrix: Vec<Vec<f32>> = vec![];
-/// let path = find_path(matrix, true);
+/// let path = find_path(matrix, true, true).unwrap();
 /// assert_eq!(path, Vec::<(usize, usize)>::new());
 /// ```
 ///
-pub fn find_path(matrix: Vec<Vec<f32>>, flexible_start: bool) -> Vec<(usize, usize)> {
+pub fn find_path(
+    matrix: Vec<Vec<f32>>,
+    flexible_start: bool,
+    flexible_end: bool,
+) -> Option<Vec<(usize, usize)>> {
     if matrix.len() == 0 {
-        return vec![];
+        return Some(vec![]);
     }
     if matrix[0].len() == 0 {
-        return vec![];
+        return Some(vec![]);
     }
 
     let matrix: Vec<Vec<OrderedFloat<f32>>> = matrix
@@ -217,9 +242,9 @@ pub fn find_path(matrix: Vec<Vec<f32>>, flexible_start: bool) -> Vec<(usize, usi
         x = 0;
         y = 0;
     }
-    let pos = Pos::start(matrix, x, y);
+    let pos = Pos::start(matrix, x, y, flexible_end);
 
-    let result = dijkstra(&pos, |p| p.successors(), |p| p.reached()).unwrap();
+    let result = dijkstra(&pos, |p| p.successors(), |p| p.reached())?;
 
     let mut path = result.0;
 
@@ -227,15 +252,17 @@ pub fn find_path(matrix: Vec<Vec<f32>>, flexible_start: bool) -> Vec<(usize, usi
         path.remove(0); // -1,-1 starting point
     }
 
-    path.iter()
-        .map(|pos| (pos.0 as usize, pos.1 as usize))
-        .collect()
+    Some(
+        path.iter()
+            .map(|pos| (pos.0 as usize, pos.1 as usize))
+            .collect(),
+    )
 }
 
 impl Pos {
-    fn start(matrix: Rc<Vec<Vec<OrderedFloat<f32>>>>, x: i32, y: i32) -> Self {
+    fn start(matrix: Rc<Vec<Vec<OrderedFloat<f32>>>>, x: i32, y: i32, flexible_end: bool) -> Self {
         let mut flat: Vec<&OrderedFloat<f32>> = matrix.iter().flatten().collect();
-        flat.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        flat.sort();
 
         let mut median: f32 = **flat[flat.len() / 2];
 
@@ -246,28 +273,34 @@ impl Pos {
         let median = OrderedFloat(median);
 
         // median is no longer median here - old naming
-        Pos(x, y, matrix, median)
+        Pos(x, y, matrix, median, flexible_end)
     }
     fn is_flexible_start(&self) -> bool {
-        let &Pos(x, _y, _, _) = self;
+        let &Pos(x, _y, _, _, _) = self;
         x == -1
     }
     // just reaching single pixel on the last row/column, could cut a bit there but not a problem
+    // (fixed end has to reach the very corner instead)
     fn reached(&self) -> bool {
-        let &Pos(x, y, _, _) = self;
-        let Pos(_, _, matrix, _) = &self;
+        let &Pos(x, y, _, _, flexible_end) = self;
+        let Pos(_, _, matrix, _, _) = &self;
         let w = matrix[0].len() as i32;
         let h = matrix.len() as i32;
 
-        x == w - 1 || y == h - 1
+        if flexible_end {
+            x == w - 1 || y == h - 1
+        } else {
+            x == w - 1 && y == h - 1
+        }
     }
 
     fn cost(&self) -> OrderedFloat<f32> {
-        let &Pos(x, y, _, median) = self;
-        let Pos(_, _, matrix, _) = &self;
+        let &Pos(x, y, _, median, _) = self;
+        let Pos(_, _, matrix, _, _) = &self;
 
+        // virtual start before the matrix is not a cell, it costs nothing
         if self.is_flexible_start() {
-            panic!("nope");
+            return OrderedFloat(0.);
         }
 
         if self.reached() {
@@ -283,8 +316,8 @@ impl Pos {
     }
 
     fn successors(&self) -> Vec<(Pos, OrderedFloat<f32>)> {
-        let &Pos(x, y, _, median) = self;
-        let Pos(_, _, matrix, _) = &self;
+        let &Pos(x, y, _, median, flexible_end) = self;
+        let Pos(_, _, matrix, _, _) = &self;
         let w = matrix[0].len() as i32;
         let h = matrix.len() as i32;
 
@@ -293,12 +326,12 @@ impl Pos {
             let mut xs = vec![];
             xs.extend(
                 (0..w)
-                    .map(|i| Pos(i, 0, matrix.clone(), median))
+                    .map(|i| Pos(i, 0, matrix.clone(), median, flexible_end))
                     .collect::<Vec<Pos>>(),
             );
             xs.extend(
                 (1..h)
-                    .map(|j| Pos(0, j, matrix.clone(), median))
+                    .map(|j| Pos(0, j, matrix.clone(), median, flexible_end))
                     .collect::<Vec<Pos>>(),
             );
 
@@ -318,13 +351,15 @@ impl Pos {
             return result;
         } else {
             vec![
-                Pos(x + 1, y + 1, matrix.clone(), median), // diagonal - most probable from good position
-                Pos(x + 1, y, matrix.clone(), median), // additional sentence on one text while same on another
-                Pos(x, y + 1, matrix.clone(), median),
+                Pos(x + 1, y + 1, matrix.clone(), median, flexible_end), // diagonal - most probable from good position
+                Pos(x + 1, y, matrix.clone(), median, flexible_end), // additional sentence on one text while same on another
+                Pos(x, y + 1, matrix.clone(), median, flexible_end),
             ]
         };
 
+        // with fixed end the search walks along last row/column, so it should not step outside
         xs.into_iter()
+            .filter(|p| p.0 < w && p.1 < h)
             .map(|x| {
                 let cost = x.cost();
                 (x, cost)
@@ -340,7 +375,7 @@ impl Pos {
 /// Types are arbitrary.
 ///
 /// ```
-/// use translate::*;
+/// use make_parallel_text::*;
 ///
 /// // simle square cases
 /// assert_eq!(cells_to_extend_diagonal(0, 0, 1, 1), 0);
@@ -1,3 +1,4 @@
+use crate::error::{AlignError, AlignResult};
 use rust_bert::pipelines::sentence_embeddings::SentenceEmbeddingsBuilder;
 use rust_bert::pipelines::sentence_embeddings::SentenceEmbeddingsModel;
 use rust_bert::pipelines::sentence_embeddings::SentenceEmbeddingsModelType;
@@ -8,41 +9,46 @@ pub struct Similarity {
 }
 
 impl Similarity {
-    pub fn new() -> Self {
+    pub fn new() -> AlignResult<Self> {
         // (for my case Rus<>Srb only the latest worked, it seems too general to not use it)
         //
         //let model = SentenceEmbeddingsBuilder::remote(SentenceEmbeddingsModelType::AllMiniLmL6V2)
         //let model = SentenceEmbeddingsBuilder::remote(SentenceEmbeddingsModelType::SentenceT5Base)
+        // `MODEL_ID` has to change along with it
         let model = SentenceEmbeddingsBuilder::remote(
             SentenceEmbeddingsModelType::DistiluseBaseMultilingualCased,
         )
         .with_device(Device::cuda_if_available())
         .create_model()
-        .unwrap();
+        .map_err(AlignError::ModelLoad)?;
 
-        Similarity { model }
+        Ok(Similarity { model })
     }
 
-    pub fn get_many(&self, xs: &[&str]) -> Vec<Vec<f32>> {
-        let embeddings: Vec<Vec<f32>> = self.model.encode(&xs).unwrap();
-        let tensor = cosine_similarity_matrix(&embeddings);
+    pub fn get_many(&self, xs: &[&str]) -> AlignResult<Vec<Vec<f32>>> {
+        let embeddings: Vec<Vec<f32>> =
+            self.model.encode(&xs).map_err(|source| AlignError::Model {
+                sentences: xs.len(),
+                source,
+            })?;
+        let tensor = cosine_similarity_matrix(&embeddings)?;
 
         let shape = tensor.size();
         let flat = tensor.flatten(0, -1);
-        let v = Vec::<f32>::try_from(flat).expect("wrong type of tensor");
+        let v = Vec::<f32>::try_from(flat).map_err(AlignError::Tensor)?;
 
         //let rows = shape[0] as usize;
         let cols = shape[1] as usize;
         let nested_data: Vec<Vec<f32>> = v.chunks(cols).map(|chunk| chunk.to_vec()).collect();
 
-        nested_data
+        Ok(nested_data)
     }
 }
 
-fn cosine_similarity_matrix(embeddings: &[Vec<f32>]) -> tch::Tensor {
+fn cosine_similarity_matrix(embeddings: &[Vec<f32>]) -> AlignResult<tch::Tensor> {
     let n = embeddings.len();
     if n == 0 {
-        panic!("No embeddings provided!");
+        return Err(AlignError::EmptyInput("window of sentences".to_string()));
     }
 
     let d = embeddings[0].len();
@@ -51,7 +57,10 @@ fn cosine_similarity_matrix(embeddings: &[Vec<f32>]) -> tch::Tensor {
     // Flatten the Vec<Vec<f32>> into a single Vec<f32>
     for embed in embeddings {
         if embed.len() != d {
-            panic!("All embeddings must have the same dimension.");
+            return Err(AlignError::EmbeddingSize {
+                expected: d,
+                found: embed.len(),
+            });
         }
         data.extend_from_slice(embed);
     }
@@ -70,6 +79,5 @@ fn cosine_similarity_matrix(embeddings: &[Vec<f32>]) -> tch::Tensor {
 
     // Cosine similarity is just the matrix multiplication
     // of normalized with its transpose
-    let similarity = normalized.matmul(&normalized.transpose(0, 1));
-    similarity
+    Ok(normalized.matmul(&normalized.transpose(0, 1)))
 }
@@ -1,3 +1,8 @@
+use crate::error::{AlignError, AlignResult};
+
+// recorded in state manifest, has to change along with splitting rules below
+pub const SPLITTER_ID: &str = "lines-.!?-lowercase";
+
 pub fn split_into_sentences(input: &str) -> Vec<String> {
     let chars = &['.', '!', '?'];
     let strings = &[".", "!", "?"];
@@ -13,3 +18,13 @@ pub fn split_into_sentences(input: &str) -> Vec<String> {
         })
         .collect()
 }
+
+/// Sentences of a text file, empty one is an error.
+pub fn sentences_from_file(file: &str) -> AlignResult<Vec<String>> {
+    let text = std::fs::read_to_string(file).map_err(AlignError::io(file))?;
+    let sentences = split_into_sentences(&text);
+    if sentences.is_empty() {
+        return Err(AlignError::EmptyInput(file.to_string()));
+    }
+    Ok(sentences)
+}
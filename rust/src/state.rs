@@ -0,0 +1,147 @@
+use crate::error::{AlignError, AlignResult};
+use glob::glob;
+use regex::Regex;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufReader, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+/// Writes the file so that it is either complete or absent.
+///
+/// Content goes into `<file>.tmp` first, which is renamed over `file` once it is on disk.
+/// Killed process leaves at most that temp file behind, it is overwritten next time.
+pub fn write_atomic(file: &str, contents: impl AsRef<[u8]>) -> AlignResult<()> {
+    let tmp = format!("{}.tmp", file);
+
+    let mut out = File::create(&tmp).map_err(AlignError::io(&tmp))?;
+    out.write_all(contents.as_ref())
+        .and_then(|_| out.sync_all())
+        .map_err(AlignError::io(&tmp))?;
+    drop(out);
+
+    std::fs::rename(&tmp, file).map_err(AlignError::io(file))?;
+    Ok(())
+}
+
+pub fn write_json_atomic<T: Serialize + ?Sized>(file: &str, data: &T) -> AlignResult<()> {
+    let json = serde_json::to_string(data).map_err(|e| AlignError::Io {
+        file: file.to_string(),
+        source: e.into(),
+    })?;
+    write_atomic(file, json)
+}
+
+/// Reads state file written earlier, `None` if there is no file.
+///
+/// File that can not be parsed (left by older non-atomic writes) is removed so that it gets
+/// computed again, and reported as `StateCorrupted`.
+///
+/// ```
+/// use make_parallel_text::*;
+///
+/// let dir = tempfile::tempdir()?;
+/// let file = dir.path().join("path-0-0-0.json");
+/// let file = file.to_str().unwrap();
+///
+/// write_json_atomic(file, &vec![(0, 0), (1, 2)])?;
+/// assert_eq!(read_json_valid::<Vec<(usize, usize)>>(file)?, Some(vec![(0, 0), (1, 2)]));
+///
+/// std::fs::write(file, "[[0, 0], [1,")?;
+/// let error = read_json_valid::<Vec<(usize, usize)>>(file).unwrap_err();
+/// assert!(matches!(error, AlignError::StateCorrupted { .. }));
+/// assert_eq!(read_json_valid::<Vec<(usize, usize)>>(file)?, None);
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn read_json_valid<T: DeserializeOwned>(file: &str) -> AlignResult<Option<T>> {
+    let Some(reader) = File::open(file).ok().map(BufReader::new) else {
+        return Ok(None);
+    };
+
+    serde_json::from_reader(reader).map(Some).map_err(|e| {
+        std::fs::remove_file(file).ok();
+        AlignError::StateCorrupted {
+            file: file.to_string(),
+            reason: e.to_string(),
+        }
+    })
+}
+
+/// Files of a single alignment window in the state dir.
+#[derive(Debug)]
+pub struct WindowFile {
+    pub iteration: usize,
+    pub left_start: usize,
+    pub right_start: usize,
+    pub path_file: String,
+}
+
+impl WindowFile {
+    /// Path found in the window, indices are relative to the window start.
+    pub fn path(&self) -> AlignResult<Vec<(usize, usize)>> {
+        let reader =
+            BufReader::new(File::open(&self.path_file).map_err(AlignError::io(&self.path_file))?);
+        serde_json::from_reader(reader).map_err(|e| AlignError::StateCorrupted {
+            file: self.path_file.clone(),
+            reason: e.to_string(),
+        })
+    }
+
+    /// Similarity matrix file of the window if it was saved: binary one or JSON of older runs.
+    pub fn matrix_file(&self) -> Option<String> {
+        let file = Path::new(&self.path_file).with_file_name(format!(
+            "matrix-{}-{}-{}.json",
+            self.iteration, self.left_start, self.right_start
+        ));
+        [file.with_extension("bin"), file]
+            .into_iter()
+            .find(|file| file.exists())
+            .map(|file| file.to_string_lossy().to_string())
+    }
+}
+
+/// All windows found in the state dir ordered by iteration.
+pub fn window_files(dir: &str) -> AlignResult<Vec<WindowFile>> {
+    // filenames like "path-{iteration}-{left_start}-{right_start}.json"
+    let re = Regex::new(r"path-(\d+)-(\d+)-(\d+)\.json$").expect("valid pattern");
+
+    let mut files = Vec::new();
+    for entry in state_files(dir, "path-*-*-*.json")? {
+        let path_file = entry.to_string_lossy().to_string();
+        if let Some(caps) = re.captures(&path_file) {
+            let number = |i: usize| {
+                caps[i].parse().map_err(|_| AlignError::StateCorrupted {
+                    file: path_file.clone(),
+                    reason: "number in the name is too big".to_string(),
+                })
+            };
+            files.push(WindowFile {
+                iteration: number(1)?,
+                left_start: number(2)?,
+                right_start: number(3)?,
+                path_file: path_file.clone(),
+            });
+        }
+    }
+    files.sort_by_key(|file| file.iteration);
+
+    Ok(files)
+}
+
+/// Files in the state dir matching a glob pattern like "path-*.json".
+pub fn state_files(dir: &str, pattern: &str) -> AlignResult<Vec<PathBuf>> {
+    let pattern = format!("{}/{}", dir, pattern);
+    let entries = glob(&pattern).map_err(|e| AlignError::Io {
+        file: pattern.clone(),
+        source: std::io::Error::new(ErrorKind::InvalidInput, e),
+    })?;
+
+    entries
+        .map(|entry| {
+            entry.map_err(|e| AlignError::Io {
+                file: e.path().to_string_lossy().to_string(),
+                source: e.into_error(),
+            })
+        })
+        .collect()
+}
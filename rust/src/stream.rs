@@ -0,0 +1,125 @@
+use crate::confidence::Similarities;
+use crate::html::{block_indices, LRPair};
+use crate::options::Format;
+use anyhow::*;
+
+/// Aligned block with what outputs need to render it.
+pub struct Block<'a> {
+    pub index: usize,
+    /// sentence indices of left side
+    pub left: Vec<usize>,
+    /// sentence indices of right side
+    pub right: Vec<usize>,
+    pub left_sentences: &'a [String],
+    pub right_sentences: &'a [String],
+    /// mean similarity of pairs, known when matrices are saved
+    pub similarity: Option<f32>,
+}
+
+impl<'a> Block<'a> {
+    pub fn new(
+        index: usize,
+        pairs: &'a [LRPair],
+        left_sentences: &'a [String],
+        right_sentences: &'a [String],
+        similarities: Option<&Similarities>,
+    ) -> Self {
+        let (left, right) = block_indices(pairs);
+        Self {
+            index,
+            left,
+            right,
+            left_sentences,
+            right_sentences,
+            similarity: similarities.and_then(|similarities| similarities.block(pairs)),
+        }
+    }
+
+    pub fn left_texts(&self) -> impl Iterator<Item = &'a String> + '_ {
+        self.left.iter().filter_map(|&i| self.left_sentences.get(i))
+    }
+
+    pub fn right_texts(&self) -> impl Iterator<Item = &'a String> + '_ {
+        self.right
+            .iter()
+            .filter_map(|&i| self.right_sentences.get(i))
+    }
+}
+
+/// Output written block by block, so that no document is built whole in memory.
+pub trait BlockWriter {
+    /// Called once before the first block, for headers.
+    fn begin(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn block(&mut self, block: &Block) -> Result<()>;
+
+    /// Called once after the last block, for footers and flushing.
+    fn end(&mut self) -> Result<()>;
+}
+
+/// Feeds every block of the sequence to all writers in one pass.
+pub fn write_blocks(
+    left_sentences: &[String],
+    right_sentences: &[String],
+    sequence: &[Vec<LRPair>],
+    similarities: Option<&Similarities>,
+    writers: &mut [Box<dyn BlockWriter + '_>],
+) -> Result<()> {
+    for writer in writers.iter_mut() {
+        writer.begin()?;
+    }
+    for (index, pairs) in sequence.iter().enumerate() {
+        let block = Block::new(index, pairs, left_sentences, right_sentences, similarities);
+        for writer in writers.iter_mut() {
+            writer.block(&block)?;
+        }
+    }
+    for writer in writers.iter_mut() {
+        writer.end()?;
+    }
+    Ok(())
+}
+
+/// File name of an output in the state dir.
+pub fn file_name(format: Format) -> &'static str {
+    match format {
+        Format::ThreeColumns => "3-columns.html",
+        Format::TwoColumns => "2-columns.html",
+        Format::OneColumn => "1-column.html",
+        Format::CustomColumns => "custom-columns.html",
+        Format::Interlinear => "interlinear.html",
+        Format::InterlinearMd => "interlinear.md",
+        Format::Reader => "reader.html",
+        Format::Epub => "book.epub",
+        Format::TwoColumnsPdf => "2-columns.pdf",
+        Format::ThreeColumnsPdf => "3-columns.pdf",
+        Format::Tmx => "aligned.tmx",
+        Format::Tsv => "aligned.tsv",
+        Format::Csv => "aligned.csv",
+        Format::Jsonl => "aligned.jsonl",
+        Format::Anki => "anki.tsv",
+        Format::Overview => "overview.png",
+        Format::Report => "report.html",
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::html::get_sequence;
+
+    /// Runs a writer over sentences aligned one to one, a block per pair.
+    pub(crate) fn write_one_to_one(
+        left: &[&str],
+        right: &[&str],
+        writer: impl BlockWriter,
+    ) -> Result<()> {
+        let sentences = |texts: &[&str]| texts.iter().map(|text| text.to_string()).collect();
+        let (left, right): (Vec<String>, Vec<String>) = (sentences(left), sentences(right));
+        let path: Vec<(usize, usize)> = (0..left.len().min(right.len())).map(|i| (i, i)).collect();
+        let mut writers: Vec<Box<dyn BlockWriter + '_>> = vec![Box::new(writer)];
+        write_blocks(&left, &right, &get_sequence(&path), None, &mut writers)
+    }
+}
@@ -0,0 +1,106 @@
+use crate::export::ExportBlock;
+use crate::html::escape_html;
+use crate::language::UNKNOWN_LANGUAGE;
+use crate::options::OutputOptions;
+use crate::stream::{Block, BlockWriter};
+use anyhow::*;
+use std::io::Write;
+
+/// TMX 1.4 translation memory: a translation unit per block, left text is the source.
+pub struct TmxWriter<'a, W: Write> {
+    out: W,
+    args: &'a OutputOptions,
+}
+
+impl<'a, W: Write> TmxWriter<'a, W> {
+    pub fn new(out: W, args: &'a OutputOptions) -> Self {
+        Self { out, args }
+    }
+}
+
+impl<W: Write> BlockWriter for TmxWriter<'_, W> {
+    fn begin(&mut self) -> Result<()> {
+        let source_lang = if self.args.left_lang == UNKNOWN_LANGUAGE {
+            "*all*"
+        } else {
+            &self.args.left_lang
+        };
+
+        write!(
+            self.out,
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<tmx version="1.4">
+  <header creationtool="make-parallel-text" creationtoolversion="{version}" segtype="block" o-tmf="make-parallel-text" adminlang="en" srclang="{source_lang}" datatype="plaintext"/>
+  <body>
+"#,
+            version = env!("CARGO_PKG_VERSION"),
+            source_lang = escape_html(source_lang),
+        )?;
+        Ok(())
+    }
+
+    fn block(&mut self, block: &Block) -> Result<()> {
+        let Some(block) = ExportBlock::from_block(block, self.args) else {
+            return Ok(());
+        };
+
+        let mut tu = format!("    <tu tuid=\"{}\">\n", block.block);
+        if let Some(similarity) = block.similarity {
+            tu.push_str(&format!(
+                "      <prop type=\"x-similarity\">{:.3}</prop>\n",
+                similarity
+            ));
+        }
+        for (lang, text) in [
+            (&self.args.left_lang, &block.left_text),
+            (&self.args.right_lang, &block.right_text),
+        ] {
+            tu.push_str(&format!(
+                "      <tuv xml:lang=\"{}\"><seg>{}</seg></tuv>\n",
+                escape_html(lang),
+                escape_html(text)
+            ));
+        }
+        tu.push_str("    </tu>\n");
+        self.out.write_all(tu.as_bytes())?;
+        Ok(())
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.out.write_all(b"  </body>\n</tmx>\n")?;
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stream::tests::write_one_to_one;
+
+    #[test]
+    fn texts_are_xml_escaped() -> Result<()> {
+        let options = OutputOptions::default();
+        let mut out = Vec::new();
+        write_one_to_one(
+            &["Fish & \"chips\""],
+            &["a < b"],
+            TmxWriter::new(&mut out, &options),
+        )?;
+
+        let tmx = String::from_utf8(out)?;
+        assert!(tmx.contains("<seg>Fish &amp; &quot;chips&quot;</seg>"));
+        assert!(tmx.contains("<seg>a &lt; b</seg>"));
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_source_language_is_all() -> Result<()> {
+        let options = OutputOptions::default();
+        let mut out = Vec::new();
+        write_one_to_one(&["One."], &["Uno."], TmxWriter::new(&mut out, &options))?;
+
+        assert!(String::from_utf8(out)?.contains("srclang=\"*all*\""));
+        Ok(())
+    }
+}